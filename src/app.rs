@@ -1,13 +1,17 @@
+use chrono::Local;
 use log::info;
 
 use crate::{
     calendar::CalendarState,
     clock::ClockState,
-    db::DB,
+    db::{DbError, DB},
+    exchange_rates::ExchangeRatesState,
     film_tracker::FilmTrackerState,
-    grade_tracker::GradeTrackerState,
+    grade_tracker::{self, GradeTrackerState},
     money_tracker::MoneyTrackerState,
+    rules_editor::RulesEditorState,
     screens::Screen,
+    todo_list::TodoState,
     util::{get_brightness, get_volume},
 };
 
@@ -21,23 +25,41 @@ pub struct App {
     pub db: DB,
     pub money_state: MoneyTrackerState,
     pub film_state: FilmTrackerState,
+    pub todo_state: TodoState,
+    pub rules_state: RulesEditorState,
+    pub rates_state: ExchangeRatesState,
+    pub error: Option<String>,
 }
 
 impl App {
-    pub async fn new() -> App {
-        let mut db = DB::new().await;
-        db.run_migrations().await;
+    pub async fn new() -> Result<App, DbError> {
+        let mut db = DB::new().await?;
+        db.run_migrations().await?;
+        let added = db
+            .add_due_recurring_transactions(Local::now().date_naive())
+            .await?;
+        info!("added {} recurring transactions", added);
+        grade_tracker::import_legacy_files(&mut db).await?;
+        let grade_state = GradeTrackerState::new(&mut db).await?;
+        let todo_state = TodoState::new(&mut db).await?;
+        let money_state = MoneyTrackerState::new(&mut db).await?;
+        let rules_state = RulesEditorState::new(&mut db).await?;
+        let rates_state = ExchangeRatesState::new(&mut db).await?;
 
-        App {
+        Ok(App {
             brightness: get_brightness(),
             volume: get_volume(),
             calendar_state: CalendarState::new().await,
-            grade_state: GradeTrackerState::new(),
+            grade_state,
             clock_state: ClockState::new(),
             cur_screen: Screen::DashboardScreen,
             db,
-            money_state: MoneyTrackerState::new(),
+            money_state,
             film_state: FilmTrackerState::new(),
-        }
+            todo_state,
+            rules_state,
+            rates_state,
+            error: None,
+        })
     }
 }
@@ -12,6 +12,7 @@ use tui::{
     widgets::{BorderType, Borders, StatefulWidget, Widget},
 };
 
+use crate::grade_tracker::Deadline;
 use crate::styles::{AppStyles, COLORS};
 use crate::util::{draw_rect_borders, generic_increment, get_calendar_events};
 use serde::Deserialize;
@@ -175,11 +176,24 @@ impl CalendarState {
     }
 }
 
-pub struct Calendar;
+pub struct Calendar {
+    /// Assessment deadlines, marked on their days alongside the events
+    deadlines: Vec<Deadline>,
+}
 
 impl Calendar {
-    pub fn new() -> Calendar {
-        Calendar {}
+    pub fn new(deadlines: Vec<Deadline>) -> Calendar {
+        Calendar { deadlines }
+    }
+
+    fn get_day_deadlines<'a>(
+        &'a self,
+        state: &CalendarState,
+        day: u32,
+    ) -> impl Iterator<Item = &'a Deadline> {
+        let date =
+            NaiveDate::from_ymd_opt(state.cur_year, state.cur_month.number_from_month(), day);
+        self.deadlines.iter().filter(move |d| Some(d.date) == date)
     }
 }
 
@@ -384,6 +398,14 @@ impl StatefulWidget for Calendar {
                         }),
                         None => {}
                     }
+                    if self.get_day_deadlines(state, day as u32).next().is_some() {
+                        buf.set_string(
+                            rect.right() - 2,
+                            rect.top() + 1,
+                            "!",
+                            AppStyles::Expense.get(),
+                        );
+                    }
                 }
             }
         }
@@ -495,6 +517,27 @@ impl StatefulWidget for Calendar {
             //                 Style::default(),
             //             );
         }
+
+        // draw deadlines at the bottom, two lines each
+        let deadlines: Vec<&Deadline> = self.get_day_deadlines(state, state.selected_day).collect();
+        let mut y = (t_area.y + t_area.height).saturating_sub(deadlines.len() as u16 * 2);
+        for deadline in deadlines {
+            buf.set_stringn(
+                t_area.x,
+                y,
+                format!("Due: {}", deadline.name),
+                t_area.width as usize,
+                AppStyles::Expense.get(),
+            );
+            buf.set_stringn(
+                t_area.x,
+                y + 1,
+                &deadline.module,
+                t_area.width as usize,
+                AppStyles::Accent.get(),
+            );
+            y += 2;
+        }
     }
 }
 
@@ -502,9 +545,14 @@ impl StatefulWidget for Calendar {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_calendar_state() {
-        let cs = CalendarState::new();
-        println!("{:?}", cs);
+    #[tokio::test]
+    async fn test_calendar_state() {
+        let cs = CalendarState::new().await;
+        let now = Local::now();
+        assert_eq!(cs.cur_year, now.year());
+        assert_eq!(cs.cur_month.number_from_month(), now.month());
+        assert_eq!(cs.selected_day, cs.cur_day);
+        assert!((28..=31).contains(&cs.num_of_days));
+        assert!(cs.start_day < 7);
     }
 }
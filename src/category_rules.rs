@@ -0,0 +1,178 @@
+use regex::{Regex, RegexBuilder};
+use sqlx::FromRow;
+
+use crate::{db::MoneyTransaction, money::Money};
+
+/// How a rule's pattern is compared with a transaction's title
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[sqlx(rename_all = "lowercase")]
+pub enum RuleKind {
+    /// Title contains the pattern, ignoring case
+    Contains,
+    /// Title matches the pattern as a regex, ignoring case
+    Regex,
+}
+
+impl RuleKind {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            RuleKind::Contains => "Contains",
+            RuleKind::Regex => "Regex",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<RuleKind> {
+        match name {
+            "Contains" => Some(RuleKind::Contains),
+            "Regex" => Some(RuleKind::Regex),
+            _ => None,
+        }
+    }
+}
+
+/// Sets the category of transactions whose title matches `pattern` and whose
+/// amount is in range, an empty pattern matches any title
+#[derive(FromRow, Debug, Clone)]
+pub struct CategoryRule {
+    pub id: i64,
+    pub kind: RuleKind,
+    pub pattern: String,
+    pub min_amount: Option<Money>,
+    pub max_amount: Option<Money>,
+    pub category_id: i64,
+    /// Name of the category, only filled in when joined with `categories`
+    #[sqlx(default)]
+    pub category: Option<String>,
+    pub position: i64,
+}
+
+impl CategoryRule {
+    pub fn new(
+        kind: RuleKind,
+        pattern: String,
+        min_amount: Option<Money>,
+        max_amount: Option<Money>,
+        category_id: i64,
+    ) -> CategoryRule {
+        CategoryRule {
+            id: -1,
+            kind,
+            pattern,
+            min_amount,
+            max_amount,
+            category_id,
+            category: None,
+            position: 0,
+        }
+    }
+
+    /// Build the regex for the pattern, also used to check a regex is valid
+    /// before saving it
+    pub fn compile(kind: RuleKind, pattern: &str) -> Result<Regex, regex::Error> {
+        let pattern = match kind {
+            RuleKind::Contains => regex::escape(pattern),
+            RuleKind::Regex => pattern.to_owned(),
+        };
+        RegexBuilder::new(&pattern).case_insensitive(true).build()
+    }
+}
+
+/// Rules compiled once so they can be checked against many transactions
+pub struct RuleSet {
+    rules: Vec<(CategoryRule, Regex)>,
+}
+
+impl RuleSet {
+    /// Rules should be in priority order, any with an invalid regex are left
+    /// out
+    pub fn new(rules: Vec<CategoryRule>) -> RuleSet {
+        RuleSet {
+            rules: rules
+                .into_iter()
+                .filter_map(|r| {
+                    let regex = CategoryRule::compile(r.kind, &r.pattern).ok()?;
+                    Some((r, regex))
+                })
+                .collect(),
+        }
+    }
+
+    /// Category from the first rule matching the transaction
+    pub fn category_for(&self, transaction: &MoneyTransaction) -> Option<i64> {
+        self.rules
+            .iter()
+            .find(|(rule, regex)| {
+                regex.is_match(&transaction.title)
+                    && rule.min_amount.is_none_or(|min| transaction.amount >= min)
+                    && rule.max_amount.is_none_or(|max| transaction.amount <= max)
+            })
+            .map(|(rule, _)| rule.category_id)
+    }
+
+    /// Set the category of transactions that don't have one yet
+    pub fn categorise(&self, transactions: &mut [MoneyTransaction]) {
+        for transaction in transactions.iter_mut() {
+            if transaction.category_id.is_none() {
+                transaction.category_id = self.category_for(transaction);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::db::TransactionKind;
+
+    fn transaction(title: &str, amount: i64) -> MoneyTransaction {
+        MoneyTransaction::new(
+            title.to_owned(),
+            Money::from_minor(amount),
+            "".to_owned(),
+            Utc::now(),
+            TransactionKind::Expense,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_category_for() {
+        let rules = RuleSet::new(vec![
+            CategoryRule::new(RuleKind::Contains, "tesco".to_owned(), None, None, 1),
+            CategoryRule::new(
+                RuleKind::Regex,
+                r"^(uber|tfl)\b".to_owned(),
+                None,
+                Some(Money::from_minor(5000)),
+                2,
+            ),
+            // invalid regex is skipped
+            CategoryRule::new(RuleKind::Regex, "(".to_owned(), None, None, 3),
+            CategoryRule::new(
+                RuleKind::Contains,
+                "".to_owned(),
+                Some(Money::from_minor(100000)),
+                None,
+                4,
+            ),
+        ]);
+        assert_eq!(
+            rules.category_for(&transaction("TESCO STORES", 1000)),
+            Some(1)
+        );
+        // contains matches the text literally
+        assert_eq!(rules.category_for(&transaction("tesc.", 1000)), None);
+        assert_eq!(rules.category_for(&transaction("Uber trip", 1500)), Some(2));
+        assert_eq!(rules.category_for(&transaction("Uber trip", 6000)), None);
+        assert_eq!(rules.category_for(&transaction("my uber", 1500)), None);
+        assert_eq!(rules.category_for(&transaction("Rent", 120000)), Some(4));
+
+        let mut transactions = vec![transaction("tesco", 100), transaction("tesco", 100)];
+        transactions[1].category_id = Some(9);
+        rules.categorise(&mut transactions);
+        assert_eq!(transactions[0].category_id, Some(1));
+        assert_eq!(transactions[1].category_id, Some(9));
+    }
+}
@@ -0,0 +1,100 @@
+use std::fs;
+
+use log::error;
+use serde::Deserialize;
+
+use crate::paths::PATHS;
+
+lazy_static! {
+    pub static ref CONFIG: Config = Config::load();
+}
+
+/// User settings read from `config.json` in the config directory, any missing
+/// value uses its default
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct Config {
+    pub currency: CurrencyConfig,
+    pub csv_import: CsvImportConfig,
+    pub grades: GradesConfig,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct CurrencyConfig {
+    /// Code of the base currency, e.g. `GBP`, other currencies are converted
+    /// into it for totals
+    pub code: String,
+    pub symbol: String,
+}
+
+impl Default for CurrencyConfig {
+    fn default() -> Self {
+        CurrencyConfig {
+            code: "GBP".to_owned(),
+            symbol: "£".to_owned(),
+        }
+    }
+}
+
+/// Which columns of a bank's CSV export hold each value, counting from 0
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CsvImportConfig {
+    pub delimiter: char,
+    pub has_header: bool,
+    /// `chrono` format string, e.g. `%d/%m/%Y`
+    pub date_format: String,
+    pub date_column: usize,
+    pub title_column: usize,
+    /// Signed amount with money going out as negative, or only the money going
+    /// out when `credit_column` is set
+    pub amount_column: usize,
+    /// Money coming in, for exports that split it into its own column
+    pub credit_column: Option<usize>,
+    pub details_column: Option<usize>,
+}
+
+impl Default for CsvImportConfig {
+    fn default() -> Self {
+        CsvImportConfig {
+            delimiter: ',',
+            has_header: true,
+            date_format: "%d/%m/%Y".to_owned(),
+            date_column: 0,
+            title_column: 1,
+            amount_column: 2,
+            credit_column: None,
+            details_column: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct GradesConfig {
+    /// How much each year of study counts towards the degree, starting from
+    /// year 1, e.g. `[0, 40, 60]` for a first year that doesn't count
+    pub year_weights: Vec<f32>,
+}
+
+impl Default for GradesConfig {
+    fn default() -> Self {
+        GradesConfig {
+            year_weights: vec![0., 40., 60.],
+        }
+    }
+}
+
+impl Config {
+    fn load() -> Config {
+        let path = PATHS.get_config_path();
+        match fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+                error!("invalid config file {}: {}", path.display(), e);
+                Config::default()
+            }),
+            Err(_) => Config::default(),
+        }
+    }
+}
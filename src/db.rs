@@ -1,43 +1,214 @@
-use chrono::{DateTime, Utc};
+use std::{collections::HashMap, fmt};
+
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, NaiveTime, TimeZone, Utc};
 use log::info;
 use sqlx::{
-    migrate::MigrateDatabase, sqlite::SqliteQueryResult, Connection, Error, FromRow, Row, Sqlite,
-    SqliteConnection,
+    migrate::{MigrateDatabase, MigrateError, Migrator},
+    sqlite::SqliteQueryResult,
+    Connection, Error, FromRow, QueryBuilder, Row, Sqlite, SqliteConnection,
+};
+
+use crate::{
+    category_rules::{CategoryRule, RuleSet},
+    money::Money,
+    paths::PATHS,
+    recurrence::Recurrence,
 };
 
-use crate::util::getcwd;
+static MIGRATOR: Migrator = sqlx::migrate!("src/migrations");
 
 pub struct DB {
     pub conn: SqliteConnection,
 }
 
+#[derive(Debug)]
+pub enum DbError {
+    Connect(Error),
+    Create(Error),
+    Migrate(MigrateError),
+    Query(Error),
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::Connect(e) => write!(f, "Could not connect to database: {}", e),
+            DbError::Create(e) => write!(f, "Could not create database: {}", e),
+            DbError::Migrate(e) => write!(f, "Migration error: {}", e),
+            DbError::Query(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DbError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DbError::Connect(e) | DbError::Create(e) | DbError::Query(e) => Some(e),
+            DbError::Migrate(e) => Some(e),
+        }
+    }
+}
+
+impl From<Error> for DbError {
+    fn from(error: Error) -> Self {
+        DbError::Query(error)
+    }
+}
+
+impl From<MigrateError> for DbError {
+    fn from(error: MigrateError) -> Self {
+        DbError::Migrate(error)
+    }
+}
+
 #[derive(FromRow, Debug)]
 pub struct Todo {
-    id: i64,
-    description: String,
+    pub id: i64,
+    pub description: String,
+    pub position: i64,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub recurrence: Option<Recurrence>,
+    pub due_date: Option<NaiveDate>,
 }
 
 impl Todo {
-    pub fn new(id: i64, description: String) -> Todo {
-        Todo { id, description }
+    pub fn new(
+        description: String,
+        recurrence: Option<Recurrence>,
+        due_date: Option<NaiveDate>,
+    ) -> Todo {
+        Todo {
+            id: -1,
+            description,
+            position: 0,
+            completed_at: None,
+            created_at: Utc::now(),
+            recurrence,
+            due_date,
+        }
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.completed_at.is_some()
+    }
+
+    /// Due on or before `today` and not yet completed
+    pub fn is_due(&self, today: NaiveDate) -> bool {
+        !self.is_completed() && self.due_date.is_some_and(|d| d <= today)
+    }
+
+    /// Get the todo that replaces this one when it is completed, if it recurs
+    ///
+    /// A todo completed late is rescheduled from `today` rather than its old due
+    /// date so it doesn't immediately come back overdue
+    pub fn next_occurrence(&self, today: NaiveDate) -> Option<Todo> {
+        let recurrence = self.recurrence.as_ref()?;
+        let from = self.due_date.map_or(today, |d| d.max(today));
+        Some(Todo::new(
+            self.description.clone(),
+            Some(recurrence.clone()),
+            Some(recurrence.next_after(from)),
+        ))
     }
 }
 
-#[derive(FromRow, Debug)]
+/// A piece of work in a module
+#[derive(FromRow, Debug, Clone)]
+pub struct Grade {
+    pub id: i64,
+    pub module_id: i64,
+    pub name: String,
+    /// `None` until the work has been marked
+    pub percentage: Option<f32>,
+    /// Percentage of the module mark the assessment counts for
+    pub weight: f32,
+    pub due_date: Option<NaiveDate>,
+}
+
+impl Grade {
+    pub fn new(name: String, percentage: Option<f32>, weight: f32) -> Grade {
+        Grade {
+            id: -1,
+            module_id: -1,
+            name,
+            percentage,
+            weight,
+            due_date: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub id: i64,
+    pub name: String,
+    /// Year of study the module was taken in, starting from 1
+    pub year: u32,
+    pub term: Option<String>,
+    /// How much the module counts towards its year's average
+    pub credits: u32,
+    pub grades: Vec<Grade>,
+}
+
+impl Module {
+    pub fn new(name: String, year: u32) -> Module {
+        Module {
+            id: -1,
+            name,
+            year,
+            term: None,
+            credits: 15,
+            grades: Vec::new(),
+        }
+    }
+
+    /// Assessments that have been marked, with their marks
+    pub fn marked_grades(&self) -> impl Iterator<Item = (&Grade, f32)> {
+        self.grades.iter().filter_map(|g| Some((g, g.percentage?)))
+    }
+
+    /// Average of the marked assessments weighted by how much each counts for,
+    /// `None` until something has been marked
+    pub fn mark(&self) -> Option<f32> {
+        let weight: f32 = self.marked_grades().map(|(g, _)| g.weight).sum();
+        if weight <= 0. {
+            return None;
+        }
+        let total: f32 = self.marked_grades().map(|(g, p)| p * g.weight).sum();
+        Some(total / weight)
+    }
+}
+
+#[derive(FromRow, Debug, Clone)]
 pub struct MoneyTransaction {
     pub id: i64,
     pub title: String,
-    pub amount: f32,
+    /// Always positive, `kind` says which way the money went
+    pub amount: Money,
     pub details: String,
     pub date: DateTime<Utc>,
+    pub kind: TransactionKind,
+    pub category_id: Option<i64>,
+    /// Name of the category, only filled in when joined with `categories`
+    #[sqlx(default)]
+    pub category: Option<String>,
+    pub account_id: i64,
+    /// Id of the other half when the transaction is a transfer between
+    /// accounts
+    pub transfer_id: Option<i64>,
+    /// Currency code when the amount isn't in the base currency
+    pub currency: Option<String>,
 }
 
 impl MoneyTransaction {
     pub fn new(
         title: String,
-        amount: f32,
+        amount: Money,
         details: String,
         date: DateTime<Utc>,
+        kind: TransactionKind,
+        category_id: Option<i64>,
     ) -> MoneyTransaction {
         MoneyTransaction {
             id: -1,
@@ -45,6 +216,528 @@ impl MoneyTransaction {
             amount,
             details,
             date,
+            kind,
+            category_id,
+            category: None,
+            account_id: DEFAULT_ACCOUNT_ID,
+            transfer_id: None,
+            currency: None,
+        }
+    }
+
+    /// Amount with expenses as negative
+    pub fn signed_amount(&self) -> Money {
+        match self.kind {
+            TransactionKind::Income => self.amount,
+            TransactionKind::Expense => -self.amount,
+        }
+    }
+
+    /// Signed amount in the transaction's own currency, e.g. `-USD 4.50` or
+    /// `-£4.50` for the base currency
+    pub fn format_amount(&self) -> String {
+        match &self.currency {
+            Some(code) => self.signed_amount().format_code(code, true),
+            None => self.signed_amount().format(true),
+        }
+    }
+}
+
+/// A file kept with a transaction, such as a receipt scan
+#[derive(FromRow, Debug, Clone)]
+pub struct Attachment {
+    pub id: i64,
+    pub transaction_id: i64,
+    pub path: String,
+}
+
+/// How much one unit of a currency was worth in the base currency from a date
+#[derive(FromRow, Debug, Clone, PartialEq)]
+pub struct ExchangeRate {
+    pub currency: String,
+    pub date: NaiveDate,
+    pub rate: f64,
+}
+
+/// Account that transactions go in when no other is picked, it is created with
+/// the database
+pub const DEFAULT_ACCOUNT_ID: i64 = 1;
+
+/// Somewhere money is kept, such as a bank account or cash
+#[derive(FromRow, Debug, Clone)]
+pub struct Account {
+    pub id: i64,
+    pub name: String,
+    /// Balance before any of the account's transactions
+    pub opening_balance: Money,
+    /// Opening balance plus all the account's transactions, only filled in by
+    /// `get_accounts`
+    #[sqlx(default)]
+    pub balance: Money,
+}
+
+/// Whether money came in or went out, the amount itself is never negative
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[sqlx(rename_all = "lowercase")]
+pub enum TransactionKind {
+    Expense,
+    Income,
+}
+
+impl TransactionKind {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            TransactionKind::Expense => "Expense",
+            TransactionKind::Income => "Income",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<TransactionKind> {
+        match name {
+            "Expense" => Some(TransactionKind::Expense),
+            "Income" => Some(TransactionKind::Income),
+            _ => None,
+        }
+    }
+
+    pub fn opposite(&self) -> TransactionKind {
+        match self {
+            TransactionKind::Expense => TransactionKind::Income,
+            TransactionKind::Income => TransactionKind::Expense,
+        }
+    }
+}
+
+#[derive(FromRow, Debug)]
+pub struct Category {
+    pub id: i64,
+    pub name: String,
+}
+
+/// Spending limit for a month, carried on to later months until replaced
+#[derive(FromRow, Debug)]
+pub struct Budget {
+    /// `None` for a budget covering all spending
+    pub category_id: Option<i64>,
+    /// Name of the category, only filled in when joined with `categories`
+    #[sqlx(default)]
+    pub category: Option<String>,
+    pub amount: Money,
+}
+
+/// Transaction that repeats on a schedule, such as rent or a subscription
+#[derive(FromRow, Debug, Clone)]
+pub struct RecurringTransaction {
+    pub id: i64,
+    pub title: String,
+    pub amount: Money,
+    pub details: String,
+    pub kind: TransactionKind,
+    pub category_id: Option<i64>,
+    /// Name of the category, only filled in when joined with `categories`
+    #[sqlx(default)]
+    pub category: Option<String>,
+    pub recurrence: Recurrence,
+    /// Date of the next occurrence that hasn't been added to the transactions
+    pub next_date: NaiveDate,
+    pub account_id: i64,
+}
+
+impl RecurringTransaction {
+    pub fn new(
+        title: String,
+        amount: Money,
+        details: String,
+        kind: TransactionKind,
+        category_id: Option<i64>,
+        recurrence: Recurrence,
+        next_date: NaiveDate,
+    ) -> RecurringTransaction {
+        RecurringTransaction {
+            id: -1,
+            title,
+            amount,
+            details,
+            kind,
+            category_id,
+            category: None,
+            recurrence,
+            next_date,
+            account_id: DEFAULT_ACCOUNT_ID,
+        }
+    }
+
+    /// Amount of each occurrence with expenses as negative
+    pub fn signed_amount(&self) -> Money {
+        match self.kind {
+            TransactionKind::Income => self.amount,
+            TransactionKind::Expense => -self.amount,
+        }
+    }
+
+    /// Average signed amount per month
+    pub fn monthly_amount(&self) -> Money {
+        let monthly = self.signed_amount().minor() as f64 * self.recurrence.times_per_year() / 12.0;
+        Money::from_minor(monthly.round() as i64)
+    }
+
+    /// The transaction for an occurrence on the given date
+    fn occurrence(&self, date: NaiveDate) -> MoneyTransaction {
+        let mut transaction = MoneyTransaction::new(
+            self.title.clone(),
+            self.amount,
+            self.details.clone(),
+            Utc.from_utc_datetime(&date.and_time(NaiveTime::MIN)),
+            self.kind,
+            self.category_id,
+        );
+        transaction.account_id = self.account_id;
+        transaction
+    }
+}
+
+/// Selects transactions with the name of their category
+const TRANSACTION_SELECT: &str = r#"
+    SELECT
+        transactions.*,
+        categories.name AS category
+    FROM
+        transactions
+        LEFT JOIN categories ON categories.id = transactions.category_id
+"#;
+
+const INSERT_TRANSACTION: &str = r#"
+    INSERT INTO transactions (title, amount, details, date, kind, category_id, account_id, currency)
+    VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+"#;
+
+/// Adds a module after all the others
+const INSERT_MODULE: &str = r#"
+    INSERT INTO modules (name, year, term, credits, position)
+    VALUES (?, ?, ?, ?, (SELECT COALESCE(MAX(position), 0) + 1 FROM modules))
+"#;
+
+/// Amount of a transaction in the base currency, converted with the latest rate
+/// on or before its date, or the earliest rate if it is older than all of them
+///
+/// A currency without any rates converts to zero so it is left out of totals
+/// rather than counted as if it were the base currency
+macro_rules! base_amount {
+    () => {
+        r#"CAST(ROUND(transactions.amount * COALESCE(
+            (
+                SELECT rate FROM exchange_rates
+                WHERE currency = transactions.currency AND date <= date(transactions.date)
+                ORDER BY date DESC LIMIT 1
+            ),
+            (
+                SELECT rate FROM exchange_rates
+                WHERE currency = transactions.currency
+                ORDER BY date LIMIT 1
+            ),
+            CASE WHEN transactions.currency IS NULL THEN 1 ELSE 0 END
+        )) AS INTEGER)"#
+    };
+}
+
+/// Amount in the base currency for use in aggregate queries
+const BASE_AMOUNT: &str = base_amount!();
+
+/// Signed amount in the base currency for use in aggregate queries
+const SIGNED_AMOUNT: &str = concat!(
+    "CASE transactions.kind WHEN 'income' THEN 1 ELSE -1 END * ",
+    base_amount!()
+);
+
+/// Position in a sorted list of transactions for keyset pagination, a page
+/// starts at the first transaction at or after its cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransactionCursor {
+    pub amount: Money,
+    pub date: DateTime<Utc>,
+    pub id: i64,
+}
+
+impl TransactionCursor {
+    /// Cursor pointing at a transaction
+    pub fn at(transaction: &MoneyTransaction) -> TransactionCursor {
+        TransactionCursor {
+            amount: transaction.amount,
+            date: transaction.date,
+            id: transaction.id,
+        }
+    }
+
+    /// Cursor pointing before the first transaction on `date` when sorted by
+    /// date, in either direction
+    pub fn on_date(date: NaiveDate, sort: TransactionSort) -> TransactionCursor {
+        let (date, id) = match sort {
+            // newest first, so start just before midnight at the end of the day
+            TransactionSort::DateDesc => (date + Duration::days(1), i64::MIN),
+            _ => (date, i64::MIN),
+        };
+        TransactionCursor {
+            amount: Money::default(),
+            date: Utc.from_utc_datetime(&date.and_time(NaiveTime::MIN)),
+            id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionSort {
+    DateDesc,
+    DateAsc,
+    AmountDesc,
+    AmountAsc,
+}
+
+impl TransactionSort {
+    /// Columns the transactions are ordered by, always ending with the id so
+    /// every transaction has a distinct position that a cursor can point to
+    ///
+    /// Columns are negated where needed so they can all be sorted the same
+    /// way and compared with a cursor as a single row value
+    fn get_key_columns(&self) -> &'static str {
+        match self {
+            TransactionSort::DateDesc | TransactionSort::DateAsc => {
+                "(julianday(date), transactions.id)"
+            }
+            TransactionSort::AmountDesc => "(amount, julianday(date), transactions.id)",
+            TransactionSort::AmountAsc => "(amount, -julianday(date), -transactions.id)",
+        }
+    }
+
+    fn is_ascending(&self) -> bool {
+        matches!(self, TransactionSort::DateAsc | TransactionSort::AmountAsc)
+    }
+
+    /// `ORDER BY` terms for the sort, or for the opposite order when
+    /// `reverse` is set
+    fn get_order_by(&self, reverse: bool) -> String {
+        let direction = if self.is_ascending() != reverse {
+            "ASC"
+        } else {
+            "DESC"
+        };
+        self.get_key_columns()
+            .trim_matches(|c| c == '(' || c == ')')
+            .split(", ")
+            .map(|column| format!("{} {}", column, direction))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// Append a condition keeping transactions from the cursor onwards, or
+    /// only those before it when `before` is set
+    fn push_cursor_condition<'a>(
+        &self,
+        query: &mut QueryBuilder<'a, Sqlite>,
+        cursor: &TransactionCursor,
+        before: bool,
+    ) {
+        let op = match (self.is_ascending(), before) {
+            (true, false) => ">=",
+            (false, false) => "<=",
+            (true, true) => "<",
+            (false, true) => ">",
+        };
+        query
+            .push(" AND ")
+            .push(self.get_key_columns())
+            .push(format!(" {} (", op));
+        match self {
+            TransactionSort::DateDesc | TransactionSort::DateAsc => {
+                query.push("julianday(").push_bind(cursor.date).push("), ");
+            }
+            TransactionSort::AmountDesc => {
+                query
+                    .push_bind(cursor.amount)
+                    .push(", julianday(")
+                    .push_bind(cursor.date)
+                    .push("), ");
+            }
+            TransactionSort::AmountAsc => {
+                query
+                    .push_bind(cursor.amount)
+                    .push(", -julianday(")
+                    .push_bind(cursor.date)
+                    .push("), -");
+            }
+        }
+        query.push_bind(cursor.id).push(")");
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            TransactionSort::DateDesc => "Newest",
+            TransactionSort::DateAsc => "Oldest",
+            TransactionSort::AmountDesc => "Largest",
+            TransactionSort::AmountAsc => "Smallest",
+        }
+    }
+
+    /// Cycle to the next sort order
+    pub fn next(&self) -> TransactionSort {
+        match self {
+            TransactionSort::DateDesc => TransactionSort::DateAsc,
+            TransactionSort::DateAsc => TransactionSort::AmountDesc,
+            TransactionSort::AmountDesc => TransactionSort::AmountAsc,
+            TransactionSort::AmountAsc => TransactionSort::DateDesc,
+        }
+    }
+}
+
+/// Length of time spending is grouped into for statistics, weeks start on
+/// Monday
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsPeriod {
+    Day,
+    Week,
+    Month,
+}
+
+impl StatsPeriod {
+    /// SQL giving the first day of the period a transaction falls in
+    fn get_group_by(&self) -> &'static str {
+        match self {
+            StatsPeriod::Day => "date(date)",
+            StatsPeriod::Week => {
+                "date(julianday(date(date)) - (CAST(strftime('%w', date) AS INTEGER) + 6) % 7)"
+            }
+            StatsPeriod::Month => "date(date, 'start of month')",
+        }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            StatsPeriod::Day => "Daily",
+            StatsPeriod::Week => "Weekly",
+            StatsPeriod::Month => "Monthly",
+        }
+    }
+
+    /// Cycle to the next period length
+    pub fn next(&self) -> StatsPeriod {
+        match self {
+            StatsPeriod::Day => StatsPeriod::Week,
+            StatsPeriod::Week => StatsPeriod::Month,
+            StatsPeriod::Month => StatsPeriod::Day,
+        }
+    }
+
+    /// First day of the period containing `date`
+    pub fn start_of(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            StatsPeriod::Day => date,
+            StatsPeriod::Week => {
+                date - Duration::days(date.weekday().num_days_from_monday() as i64)
+            }
+            StatsPeriod::Month => date.with_day(1).unwrap(),
+        }
+    }
+
+    /// First day of the period after the one starting on `start`
+    pub fn next_start(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            StatsPeriod::Day => start + Duration::days(1),
+            StatsPeriod::Week => start + Duration::days(7),
+            StatsPeriod::Month => start + Months::new(1),
+        }
+    }
+
+    /// First day of the period before the one starting on `start`
+    pub fn prev_start(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            StatsPeriod::Day => start - Duration::days(1),
+            StatsPeriod::Week => start - Duration::days(7),
+            StatsPeriod::Month => start - Months::new(1),
+        }
+    }
+}
+
+/// Search criteria for transactions, every value is bound as a query parameter
+#[derive(Debug, Clone)]
+pub struct TransactionFilter {
+    /// Text the title must contain, `%` and `_` are matched literally
+    pub keyword: String,
+    /// Text the details must contain, `%` and `_` are matched literally
+    pub details: String,
+    pub min: Money,
+    pub max: Money,
+    pub start_date: Option<DateTime<Utc>>,
+    pub end_date: Option<DateTime<Utc>>,
+    /// Only include transactions in this account
+    pub account_id: Option<i64>,
+    pub sort: TransactionSort,
+}
+
+impl TransactionFilter {
+    pub fn new() -> TransactionFilter {
+        TransactionFilter {
+            keyword: "".to_owned(),
+            details: "".to_owned(),
+            min: Money::default(),
+            max: Money::from_major(100000),
+            start_date: None,
+            end_date: None,
+            account_id: None,
+            sort: TransactionSort::DateDesc,
+        }
+    }
+
+    /// Escape `LIKE` wildcards so the text only matches itself, for use with
+    /// `ESCAPE '\'`
+    pub fn escape_like(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            if matches!(c, '\\' | '%' | '_') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    /// Append a `WHERE` clause matching this filter to the query
+    fn push_conditions<'a>(&self, query: &mut QueryBuilder<'a, Sqlite>) {
+        query
+            .push(" WHERE amount >= ")
+            .push_bind(self.min)
+            .push(" AND amount <= ")
+            .push_bind(self.max);
+        if !self.keyword.is_empty() {
+            query
+                .push(" AND title LIKE ")
+                .push_bind(format!(
+                    "%{}%",
+                    TransactionFilter::escape_like(&self.keyword)
+                ))
+                .push(" ESCAPE '\\'");
+        }
+        if !self.details.is_empty() {
+            query
+                .push(" AND details LIKE ")
+                .push_bind(format!(
+                    "%{}%",
+                    TransactionFilter::escape_like(&self.details)
+                ))
+                .push(" ESCAPE '\\'");
+        }
+        if let Some(start_date) = self.start_date {
+            query
+                .push(" AND julianday(date) >= julianday(")
+                .push_bind(start_date)
+                .push(")");
+        }
+        if let Some(end_date) = self.end_date {
+            query
+                .push(" AND julianday(date) <= julianday(")
+                .push_bind(end_date)
+                .push(")");
+        }
+        if let Some(account_id) = self.account_id {
+            query.push(" AND account_id = ").push_bind(account_id);
         }
     }
 }
@@ -77,206 +770,1907 @@ pub struct Episode {
 }
 
 impl DB {
-    pub async fn new() -> DB {
-        let conn = SqliteConnection::connect(&DB::get_db_url()).await.unwrap();
-        DB { conn }
+    pub async fn new() -> Result<DB, DbError> {
+        DB::connect(&DB::get_db_url()).await
+    }
+
+    pub async fn connect(url: &str) -> Result<DB, DbError> {
+        let conn = SqliteConnection::connect(url)
+            .await
+            .map_err(DbError::Connect)?;
+        Ok(DB { conn })
+    }
+
+    pub async fn close(self) -> Result<(), DbError> {
+        self.conn.close().await?;
+        Ok(())
     }
 
     pub fn get_db_url() -> String {
-        format!("sqlite:///{}/src/db.sqlite3", getcwd())
+        format!("sqlite://{}", PATHS.get_db_path().display())
     }
 
     // https://tms-dev-blog.com/rust-sqlx-basics-with-sqlite/
-    pub async fn create_tables() {
+    pub async fn create_tables() -> Result<(), DbError> {
         let db_url = &DB::get_db_url();
         if !Sqlite::database_exists(db_url).await.unwrap_or(false) {
-            match Sqlite::create_database(db_url).await {
-                Err(error) => panic!("error: {}", error),
-                _ => {}
-            }
-        }
-    }
-
-    pub async fn run_migrations(&mut self) {
-        let migrations = std::path::Path::new(&getcwd()).join("src/migrations");
-        let migration_results = sqlx::migrate::Migrator::new(migrations)
-            .await
-            .unwrap()
-            .run(&mut self.conn)
-            .await;
-        match migration_results {
-            Err(error) => panic!("Migration error: {}", error),
-            _ => {}
+            Sqlite::create_database(db_url)
+                .await
+                .map_err(DbError::Create)?;
         }
+        Ok(())
     }
 
-    pub async fn get_todos(&mut self) -> Vec<Todo> {
-        sqlx::query_as::<_, Todo>("SELECT * FROM todos")
-            .fetch_all(&mut self.conn)
-            .await
-            .unwrap()
-    }
-
-    pub async fn add_todo(&mut self, todo: &Todo) {
-        sqlx::query("INSERT INTO todos (description) VALUES (?)")
-            .bind(&todo.description)
-            .execute(&mut self.conn)
-            .await
-            .unwrap();
-    }
-
-    pub async fn get_all_transactions(&mut self) -> Vec<MoneyTransaction> {
-        sqlx::query_as::<_, MoneyTransaction>(
-            "SELECT * FROM transactions ORDER BY julianday(date) DESC",
-        )
-        .fetch_all(&mut self.conn)
-        .await
-        .unwrap()
+    /// Run the migrations embedded from `src/migrations` at compile time
+    pub async fn run_migrations(&mut self) -> Result<(), DbError> {
+        MIGRATOR.run(&mut self.conn).await?;
+        Ok(())
     }
 
-    pub async fn get_num_of_transactions(&mut self, keyword: &str, min: f32, max: f32) -> u32 {
-        sqlx::query(&format!(
+    /// Outstanding todos in list order followed by completed todos, most
+    /// recently completed first
+    pub async fn get_todos(&mut self) -> Result<Vec<Todo>, DbError> {
+        Ok(sqlx::query_as::<_, Todo>(
             r#"
             SELECT
-                COUNT(*) as count
+                *
             FROM
-                transactions
-            WHERE
-                title LIKE '%{}%'
-                AND amount >= {}
-                AND amount <= {}
+                todos
+            ORDER BY
+                completed_at IS NOT NULL,
+                CASE WHEN completed_at IS NULL THEN position END,
+                julianday(completed_at) DESC
             "#,
-            keyword, min, max
-        ))
+        )
         .fetch_all(&mut self.conn)
-        .await
-        .unwrap()
-        .get(0)
-        .unwrap()
-        .try_get("count")
-        .unwrap()
+        .await?)
     }
 
-    pub async fn get_num_of_transaction_text_lines(
-        &mut self,
-        keyword: &str,
-        min: f32,
-        max: f32,
-    ) -> u32 {
-        let date_changes = sqlx::query(&format!(
+    pub async fn add_todo(&mut self, todo: &Todo) -> Result<(), DbError> {
+        sqlx::query(
             r#"
-            SELECT
-                COUNT(*)
-            FROM
-                transactions
-            WHERE
-                title LIKE '%{}%'
-                AND amount >= {}
-                AND amount <= {}
-            GROUP BY
-                date
+            INSERT INTO todos (description, position, created_at, recurrence, due_date)
+            VALUES (?, (SELECT COALESCE(MAX(position), 0) + 1 FROM todos), ?, ?, ?)
             "#,
-            keyword, min, max
-        ))
-        .fetch_all(&mut self.conn)
-        .await
-        .unwrap()
-        .len() as u32;
-
-        let num_of_transactions = self.get_num_of_transactions(keyword, min, max).await;
+        )
+        .bind(&todo.description)
+        .bind(todo.created_at)
+        .bind(&todo.recurrence)
+        .bind(todo.due_date)
+        .execute(&mut self.conn)
+        .await?;
+        Ok(())
+    }
 
-        num_of_transactions + date_changes
+    pub async fn update_todo(&mut self, todo: &Todo) -> Result<(), DbError> {
+        sqlx::query("UPDATE todos SET description = ?, recurrence = ?, due_date = ? WHERE id = ?")
+            .bind(&todo.description)
+            .bind(&todo.recurrence)
+            .bind(todo.due_date)
+            .bind(todo.id)
+            .execute(&mut self.conn)
+            .await?;
+        Ok(())
     }
 
-    pub async fn query_transactions(
+    /// Swaps the list positions of two todos
+    pub async fn swap_todo_positions(
         &mut self,
-        keyword: &str,
-        min: f32,
-        max: f32,
-        limit: u32,
-        offset: u32,
-    ) -> Vec<MoneyTransaction> {
-        sqlx::query_as::<_, MoneyTransaction>(&format!(
+        first: &Todo,
+        second: &Todo,
+    ) -> Result<(), DbError> {
+        sqlx::query(
             r#"
-            SELECT
-                *
-            FROM
-                transactions
+            UPDATE
+                todos
+            SET
+                position = CASE id WHEN ? THEN ? ELSE ? END
             WHERE
-                title LIKE '%{}%'
-                AND amount >= {}
-                AND amount <= {}
-            ORDER BY
-                julianday(date) DESC
-            LIMIT {} OFFSET {}
+                id IN (?, ?)
             "#,
-            keyword, min, max, limit, offset
-        ))
-        .fetch_all(&mut self.conn)
-        .await
-        .unwrap()
+        )
+        .bind(first.id)
+        .bind(second.position)
+        .bind(first.position)
+        .bind(first.id)
+        .bind(second.id)
+        .execute(&mut self.conn)
+        .await?;
+        Ok(())
     }
 
-    pub async fn add_transaction(&mut self, transaction: &MoneyTransaction) {
-        sqlx::query("INSERT INTO transactions (title, amount, details, date) VALUES (?, ?, ?, ?)")
-            .bind(&transaction.title)
-            .bind(&transaction.amount)
-            .bind(&transaction.details)
-            .bind(&transaction.date)
+    /// Complete a todo and add `next` as its next occurrence, unless an earlier
+    /// completion of the todo already added one
+    pub async fn complete_todo(
+        &mut self,
+        id: i64,
+        completed_at: DateTime<Utc>,
+        next: Option<&Todo>,
+    ) -> Result<(), DbError> {
+        let mut tx = self.conn.begin().await?;
+        sqlx::query("UPDATE todos SET completed_at = ? WHERE id = ?")
+            .bind(completed_at)
+            .bind(id)
+            .execute(&mut tx)
+            .await?;
+        if let Some(next) = next {
+            sqlx::query(
+                r#"
+                INSERT INTO todos (description, position, created_at, recurrence, due_date, spawned_from)
+                SELECT ?, (SELECT COALESCE(MAX(position), 0) + 1 FROM todos), ?, ?, ?, ?
+                WHERE NOT EXISTS (SELECT 1 FROM todos WHERE spawned_from = ?)
+                "#,
+            )
+            .bind(&next.description)
+            .bind(next.created_at)
+            .bind(&next.recurrence)
+            .bind(next.due_date)
+            .bind(id)
+            .bind(id)
+            .execute(&mut tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Move a completed todo back to the list, removing the next occurrence
+    /// completing it added if that hasn't been done yet
+    pub async fn uncomplete_todo(&mut self, id: i64) -> Result<(), DbError> {
+        let mut tx = self.conn.begin().await?;
+        sqlx::query("UPDATE todos SET completed_at = NULL WHERE id = ?")
+            .bind(id)
+            .execute(&mut tx)
+            .await?;
+        sqlx::query("DELETE FROM todos WHERE spawned_from = ? AND completed_at IS NULL")
+            .bind(id)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn delete_todo(&mut self, id: i64) -> Result<(), DbError> {
+        sqlx::query("DELETE FROM todos WHERE id = ?")
+            .bind(id)
             .execute(&mut self.conn)
-            .await
-            .unwrap();
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_all_transactions(&mut self) -> Result<Vec<MoneyTransaction>, DbError> {
+        Ok(sqlx::query_as::<_, MoneyTransaction>(&format!(
+            "{} ORDER BY julianday(date) DESC",
+            TRANSACTION_SELECT
+        ))
+        .fetch_all(&mut self.conn)
+        .await?)
+    }
+
+    pub async fn get_num_of_transactions(
+        &mut self,
+        filter: &TransactionFilter,
+    ) -> Result<u32, DbError> {
+        let mut query = QueryBuilder::new("SELECT COUNT(*) AS count FROM transactions");
+        filter.push_conditions(&mut query);
+        Ok(query
+            .build()
+            .fetch_one(&mut self.conn)
+            .await?
+            .try_get("count")?)
+    }
+
+    /// Number of filtered transactions that come before the cursor in the
+    /// filter's sort order
+    pub async fn get_num_of_transactions_before(
+        &mut self,
+        filter: &TransactionFilter,
+        cursor: &TransactionCursor,
+    ) -> Result<u32, DbError> {
+        let mut query = QueryBuilder::new("SELECT COUNT(*) AS count FROM transactions");
+        filter.push_conditions(&mut query);
+        filter.sort.push_cursor_condition(&mut query, cursor, true);
+        Ok(query
+            .build()
+            .fetch_one(&mut self.conn)
+            .await?
+            .try_get("count")?)
+    }
+
+    /// Up to `limit` filtered transactions starting at the cursor, or from the
+    /// start when there isn't one
+    pub async fn query_transactions(
+        &mut self,
+        filter: &TransactionFilter,
+        cursor: Option<&TransactionCursor>,
+        limit: u32,
+    ) -> Result<Vec<MoneyTransaction>, DbError> {
+        let mut query = QueryBuilder::new(TRANSACTION_SELECT);
+        filter.push_conditions(&mut query);
+        if let Some(cursor) = cursor {
+            filter.sort.push_cursor_condition(&mut query, cursor, false);
+        }
+        query
+            .push(" ORDER BY ")
+            .push(filter.sort.get_order_by(false))
+            .push(" LIMIT ")
+            .push_bind(limit);
+        Ok(query
+            .build_query_as::<MoneyTransaction>()
+            .fetch_all(&mut self.conn)
+            .await?)
+    }
+
+    /// Up to `limit` filtered transactions before the cursor, or from the end
+    /// when there isn't one, nearest to the cursor first
+    pub async fn query_transactions_before(
+        &mut self,
+        filter: &TransactionFilter,
+        cursor: Option<&TransactionCursor>,
+        limit: u32,
+    ) -> Result<Vec<MoneyTransaction>, DbError> {
+        let mut query = QueryBuilder::new(TRANSACTION_SELECT);
+        filter.push_conditions(&mut query);
+        if let Some(cursor) = cursor {
+            filter.sort.push_cursor_condition(&mut query, cursor, true);
+        }
+        query
+            .push(" ORDER BY ")
+            .push(filter.sort.get_order_by(true))
+            .push(" LIMIT ")
+            .push_bind(limit);
+        Ok(query
+            .build_query_as::<MoneyTransaction>()
+            .fetch_all(&mut self.conn)
+            .await?)
+    }
+
+    /// Every transaction matching the filter, in the filter's sort order
+    pub async fn get_filtered_transactions(
+        &mut self,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<MoneyTransaction>, DbError> {
+        let mut query = QueryBuilder::new(TRANSACTION_SELECT);
+        filter.push_conditions(&mut query);
+        query
+            .push(" ORDER BY ")
+            .push(filter.sort.get_order_by(false));
+        Ok(query
+            .build_query_as::<MoneyTransaction>()
+            .fetch_all(&mut self.conn)
+            .await?)
+    }
+
+    pub async fn add_transaction(&mut self, transaction: &MoneyTransaction) -> Result<(), DbError> {
+        sqlx::query(INSERT_TRANSACTION)
+            .bind(&transaction.title)
+            .bind(transaction.amount)
+            .bind(&transaction.details)
+            .bind(transaction.date)
+            .bind(transaction.kind)
+            .bind(transaction.category_id)
+            .bind(transaction.account_id)
+            .bind(&transaction.currency)
+            .execute(&mut self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Add many transactions at once, either all of them are added or none
+    pub async fn add_transactions(
+        &mut self,
+        transactions: &[MoneyTransaction],
+    ) -> Result<(), DbError> {
+        let mut tx = self.conn.begin().await?;
+        for transaction in transactions {
+            sqlx::query(INSERT_TRANSACTION)
+                .bind(&transaction.title)
+                .bind(transaction.amount)
+                .bind(&transaction.details)
+                .bind(transaction.date)
+                .bind(transaction.kind)
+                .bind(transaction.category_id)
+                .bind(transaction.account_id)
+                .bind(&transaction.currency)
+                .execute(&mut tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Whether a transaction with the same day, amount, kind and title has
+    /// already been added
+    pub async fn is_duplicate_transaction(
+        &mut self,
+        transaction: &MoneyTransaction,
+    ) -> Result<bool, DbError> {
+        Ok(sqlx::query(
+            r#"
+            SELECT
+                EXISTS (
+                    SELECT
+                        1
+                    FROM
+                        transactions
+                    WHERE
+                        date(date) = date(?)
+                        AND amount = ?
+                        AND kind = ?
+                        AND title = ?
+                        AND account_id = ?
+                        AND currency IS ?
+                ) AS found
+            "#,
+        )
+        .bind(transaction.date)
+        .bind(transaction.amount)
+        .bind(transaction.kind)
+        .bind(&transaction.title)
+        .bind(transaction.account_id)
+        .bind(&transaction.currency)
+        .fetch_one(&mut self.conn)
+        .await?
+        .try_get("found")?)
+    }
+
+    /// Update a transaction, for a transfer the other half is kept going the
+    /// opposite way with the same amount, date and currency
+    pub async fn update_transaction(
+        &mut self,
+        transaction: &MoneyTransaction,
+    ) -> Result<(), DbError> {
+        let mut tx = self.conn.begin().await?;
+        sqlx::query(
+            r#"
+            UPDATE
+                transactions
+            SET
+                title = ?,
+                amount = ?,
+                details = ?,
+                date = ?,
+                kind = ?,
+                category_id = ?,
+                currency = ?
+            WHERE
+                id = ?
+            "#,
+        )
+        .bind(&transaction.title)
+        .bind(transaction.amount)
+        .bind(&transaction.details)
+        .bind(transaction.date)
+        .bind(transaction.kind)
+        .bind(transaction.category_id)
+        .bind(&transaction.currency)
+        .bind(transaction.id)
+        .execute(&mut tx)
+        .await?;
+        sqlx::query(
+            r#"
+            UPDATE
+                transactions
+            SET
+                amount = ?,
+                date = ?,
+                kind = ?,
+                currency = ?
+            WHERE
+                transfer_id = ?
+            "#,
+        )
+        .bind(transaction.amount)
+        .bind(transaction.date)
+        .bind(transaction.kind.opposite())
+        .bind(&transaction.currency)
+        .bind(transaction.id)
+        .execute(&mut tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Delete a transaction, for a transfer both halves are deleted
+    pub async fn delete_transaction(&mut self, id: i64) -> Result<(), DbError> {
+        sqlx::query("DELETE FROM transactions WHERE id = ?")
+            .bind(id)
+            .execute(&mut self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Net total of each day with transactions and the balance at the end of
+    /// that day, oldest first, for one account or all of them
+    pub async fn get_daily_balances(
+        &mut self,
+        account_id: Option<i64>,
+    ) -> Result<Vec<(NaiveDate, Money, Money)>, DbError> {
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT
+                date(date) AS day,
+                SUM({}) AS total
+            FROM
+                transactions
+            WHERE
+                ? IS NULL
+                OR account_id = ?
+            GROUP BY
+                day
+            ORDER BY
+                day
+            "#,
+            SIGNED_AMOUNT
+        ))
+        .bind(account_id)
+        .bind(account_id)
+        .fetch_all(&mut self.conn)
+        .await?;
+
+        let mut balance: Money = sqlx::query(
+            r#"
+            SELECT
+                COALESCE(SUM(opening_balance), 0) AS opening
+            FROM
+                accounts
+            WHERE
+                ? IS NULL
+                OR id = ?
+            "#,
+        )
+        .bind(account_id)
+        .bind(account_id)
+        .fetch_one(&mut self.conn)
+        .await?
+        .try_get("opening")?;
+        let mut balances = Vec::with_capacity(rows.len());
+        for row in rows {
+            let total: Money = row.try_get("total")?;
+            balance += total;
+            balances.push((row.try_get("day")?, total, balance));
+        }
+        Ok(balances)
+    }
+
+    /// Accounts in the order they were added, with their current balances
+    pub async fn get_accounts(&mut self) -> Result<Vec<Account>, DbError> {
+        Ok(sqlx::query_as::<_, Account>(&format!(
+            r#"
+            SELECT
+                accounts.*,
+                accounts.opening_balance + COALESCE(SUM({}), 0) AS balance
+            FROM
+                accounts
+                LEFT JOIN transactions ON transactions.account_id = accounts.id
+            GROUP BY
+                accounts.id
+            ORDER BY
+                accounts.id
+            "#,
+            SIGNED_AMOUNT
+        ))
+        .fetch_all(&mut self.conn)
+        .await?)
+    }
+
+    /// Add an account, or set the opening balance of the one with the same
+    /// name
+    pub async fn save_account(
+        &mut self,
+        name: &str,
+        opening_balance: Money,
+    ) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT INTO accounts (name, opening_balance)
+            VALUES (?, ?)
+            ON CONFLICT (name) DO UPDATE SET opening_balance = excluded.opening_balance
+            "#,
+        )
+        .bind(name)
+        .bind(opening_balance)
+        .execute(&mut self.conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Move money between accounts, recorded as an expense in one and income
+    /// in the other that are linked so they stay together
+    pub async fn add_transfer(
+        &mut self,
+        from: &Account,
+        to: &Account,
+        amount: Money,
+        date: DateTime<Utc>,
+        details: &str,
+    ) -> Result<(), DbError> {
+        let mut tx = self.conn.begin().await?;
+        let mut ids = Vec::with_capacity(2);
+        for (title, kind, account_id) in [
+            (
+                format!("Transfer to {}", to.name),
+                TransactionKind::Expense,
+                from.id,
+            ),
+            (
+                format!("Transfer from {}", from.name),
+                TransactionKind::Income,
+                to.id,
+            ),
+        ] {
+            let result = sqlx::query(
+                r#"
+                INSERT INTO transactions (title, amount, details, date, kind, account_id, transfer_id)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(title)
+            .bind(amount)
+            .bind(details)
+            .bind(date)
+            .bind(kind)
+            .bind(account_id)
+            .bind(ids.first())
+            .execute(&mut tx)
+            .await?;
+            ids.push(result.last_insert_rowid());
+        }
+        sqlx::query("UPDATE transactions SET transfer_id = ? WHERE id = ?")
+            .bind(ids[1])
+            .bind(ids[0])
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Attachments of a transaction in the order they were added
+    pub async fn get_attachments(
+        &mut self,
+        transaction_id: i64,
+    ) -> Result<Vec<Attachment>, DbError> {
+        Ok(sqlx::query_as::<_, Attachment>(
+            "SELECT * FROM attachments WHERE transaction_id = ? ORDER BY id",
+        )
+        .bind(transaction_id)
+        .fetch_all(&mut self.conn)
+        .await?)
+    }
+
+    /// Paths of all attachments keyed by transaction id
+    pub async fn get_attachment_paths(&mut self) -> Result<HashMap<i64, Vec<String>>, DbError> {
+        let attachments = sqlx::query_as::<_, Attachment>("SELECT * FROM attachments ORDER BY id")
+            .fetch_all(&mut self.conn)
+            .await?;
+        let mut paths: HashMap<i64, Vec<String>> = HashMap::new();
+        for attachment in attachments {
+            paths
+                .entry(attachment.transaction_id)
+                .or_default()
+                .push(attachment.path);
+        }
+        Ok(paths)
+    }
+
+    pub async fn add_attachment(&mut self, transaction_id: i64, path: &str) -> Result<(), DbError> {
+        sqlx::query("INSERT INTO attachments (transaction_id, path) VALUES (?, ?)")
+            .bind(transaction_id)
+            .bind(path)
+            .execute(&mut self.conn)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_attachment(&mut self, id: i64) -> Result<(), DbError> {
+        sqlx::query("DELETE FROM attachments WHERE id = ?")
+            .bind(id)
+            .execute(&mut self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Exchange rates grouped by currency, newest first
+    pub async fn get_exchange_rates(&mut self) -> Result<Vec<ExchangeRate>, DbError> {
+        Ok(sqlx::query_as::<_, ExchangeRate>(
+            "SELECT * FROM exchange_rates ORDER BY currency, date DESC",
+        )
+        .fetch_all(&mut self.conn)
+        .await?)
+    }
+
+    /// Add exchange rates, replacing any for the same currency and date
+    pub async fn save_exchange_rates(&mut self, rates: &[ExchangeRate]) -> Result<(), DbError> {
+        let mut tx = self.conn.begin().await?;
+        for rate in rates {
+            sqlx::query(
+                "INSERT OR REPLACE INTO exchange_rates (currency, date, rate) VALUES (?, ?, ?)",
+            )
+            .bind(&rate.currency)
+            .bind(rate.date)
+            .bind(rate.rate)
+            .execute(&mut tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn delete_exchange_rate(&mut self, rate: &ExchangeRate) -> Result<(), DbError> {
+        sqlx::query("DELETE FROM exchange_rates WHERE currency = ? AND date = ?")
+            .bind(&rate.currency)
+            .bind(rate.date)
+            .execute(&mut self.conn)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_categories(&mut self) -> Result<Vec<Category>, DbError> {
+        Ok(
+            sqlx::query_as::<_, Category>("SELECT * FROM categories ORDER BY name")
+                .fetch_all(&mut self.conn)
+                .await?,
+        )
+    }
+
+    /// Get the id of the category with the given name, adding it if it doesn't
+    /// exist yet
+    pub async fn get_or_create_category(&mut self, name: &str) -> Result<i64, DbError> {
+        sqlx::query("INSERT OR IGNORE INTO categories (name) VALUES (?)")
+            .bind(name)
+            .execute(&mut self.conn)
+            .await?;
+        Ok(sqlx::query("SELECT id FROM categories WHERE name = ?")
+            .bind(name)
+            .fetch_one(&mut self.conn)
+            .await?
+            .try_get("id")?)
     }
 
-    pub async fn get_all_movies(&mut self) -> Vec<MoneyTransaction> {
-        sqlx::query_as::<_, MoneyTransaction>(
+    /// Spending in each period from `start` up to but not including `end`,
+    /// keyed by the first day of the period, periods with no spending are left
+    /// out
+    pub async fn get_spending_totals(
+        &mut self,
+        period: StatsPeriod,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<(NaiveDate, Money)>, DbError> {
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT
+                {} AS period,
+                SUM({}) AS total
+            FROM
+                transactions
+            WHERE
+                kind = 'expense'
+                AND transfer_id IS NULL
+                AND julianday(date) >= julianday(?)
+                AND julianday(date) < julianday(?)
+            GROUP BY
+                period
+            ORDER BY
+                period
+            "#,
+            period.get_group_by(),
+            BASE_AMOUNT
+        ))
+        .bind(start)
+        .bind(end)
+        .fetch_all(&mut self.conn)
+        .await?;
+
+        let mut totals = Vec::with_capacity(rows.len());
+        for row in rows {
+            totals.push((row.try_get("period")?, row.try_get("total")?));
+        }
+        Ok(totals)
+    }
+
+    /// Total spent from `start` up to but not including `end`
+    pub async fn get_spending_between(
+        &mut self,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Money, DbError> {
+        Ok(sqlx::query(&format!(
+            r#"
+            SELECT
+                COALESCE(SUM({}), 0) AS total
+            FROM
+                transactions
+            WHERE
+                kind = 'expense'
+                AND transfer_id IS NULL
+                AND julianday(date) >= julianday(?)
+                AND julianday(date) < julianday(?)
+            "#,
+            BASE_AMOUNT
+        ))
+        .bind(start)
+        .bind(end)
+        .fetch_one(&mut self.conn)
+        .await?
+        .try_get("total")?)
+    }
+
+    /// Categories with the most spending from `start` up to but not including
+    /// `end`, transactions without a category are grouped as `Uncategorised`
+    pub async fn get_top_categories(
+        &mut self,
+        start: NaiveDate,
+        end: NaiveDate,
+        limit: u32,
+    ) -> Result<Vec<(String, Money)>, DbError> {
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT
+                COALESCE(categories.name, 'Uncategorised') AS name,
+                SUM({}) AS total
+            FROM
+                transactions
+                LEFT JOIN categories ON categories.id = transactions.category_id
+            WHERE
+                transactions.kind = 'expense'
+                AND transfer_id IS NULL
+                AND julianday(date) >= julianday(?)
+                AND julianday(date) < julianday(?)
+            GROUP BY
+                transactions.category_id
+            ORDER BY
+                total DESC
+            LIMIT ?
+            "#,
+            BASE_AMOUNT
+        ))
+        .bind(start)
+        .bind(end)
+        .bind(limit)
+        .fetch_all(&mut self.conn)
+        .await?;
+
+        let mut categories = Vec::with_capacity(rows.len());
+        for row in rows {
+            categories.push((row.try_get("name")?, row.try_get("total")?));
+        }
+        Ok(categories)
+    }
+
+    /// Rules in the order they are tried
+    pub async fn get_category_rules(&mut self) -> Result<Vec<CategoryRule>, DbError> {
+        Ok(sqlx::query_as::<_, CategoryRule>(
+            r#"
+            SELECT
+                category_rules.*,
+                categories.name AS category
+            FROM
+                category_rules
+                LEFT JOIN categories ON categories.id = category_rules.category_id
+            ORDER BY
+                position,
+                category_rules.id
+            "#,
+        )
+        .fetch_all(&mut self.conn)
+        .await?)
+    }
+
+    /// Add a rule after all the existing ones
+    pub async fn add_category_rule(&mut self, rule: &CategoryRule) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT INTO category_rules (kind, pattern, min_amount, max_amount, category_id, position)
+            VALUES (?, ?, ?, ?, ?, (SELECT COALESCE(MAX(position), 0) + 1 FROM category_rules))
+            "#,
+        )
+        .bind(rule.kind)
+        .bind(&rule.pattern)
+        .bind(rule.min_amount)
+        .bind(rule.max_amount)
+        .bind(rule.category_id)
+        .execute(&mut self.conn)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn update_category_rule(&mut self, rule: &CategoryRule) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            UPDATE
+                category_rules
+            SET
+                kind = ?,
+                pattern = ?,
+                min_amount = ?,
+                max_amount = ?,
+                category_id = ?
+            WHERE
+                id = ?
+            "#,
+        )
+        .bind(rule.kind)
+        .bind(&rule.pattern)
+        .bind(rule.min_amount)
+        .bind(rule.max_amount)
+        .bind(rule.category_id)
+        .bind(rule.id)
+        .execute(&mut self.conn)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_category_rule(&mut self, id: i64) -> Result<(), DbError> {
+        sqlx::query("DELETE FROM category_rules WHERE id = ?")
+            .bind(id)
+            .execute(&mut self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Swap the order two rules are tried in
+    pub async fn swap_category_rule_positions(
+        &mut self,
+        first: &CategoryRule,
+        second: &CategoryRule,
+    ) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            UPDATE
+                category_rules
+            SET
+                position = CASE id WHEN ? THEN ? ELSE ? END
+            WHERE
+                id IN (?, ?)
+            "#,
+        )
+        .bind(first.id)
+        .bind(second.position)
+        .bind(first.position)
+        .bind(first.id)
+        .bind(second.id)
+        .execute(&mut self.conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Run the rules over every transaction, setting the category of those a
+    /// rule matches, and get how many were changed
+    ///
+    /// Transactions no rule matches keep the category they have
+    pub async fn apply_category_rules(&mut self) -> Result<u64, DbError> {
+        let rules = RuleSet::new(self.get_category_rules().await?);
+        let transactions = self.get_all_transactions().await?;
+        let mut tx = self.conn.begin().await?;
+        let mut changed = 0;
+        for transaction in transactions {
+            match rules.category_for(&transaction) {
+                Some(category_id) if transaction.category_id != Some(category_id) => {
+                    sqlx::query("UPDATE transactions SET category_id = ? WHERE id = ?")
+                        .bind(category_id)
+                        .bind(transaction.id)
+                        .execute(&mut tx)
+                        .await?;
+                    changed += 1;
+                }
+                _ => {}
+            }
+        }
+        tx.commit().await?;
+        Ok(changed)
+    }
+
+    /// Recurring transactions, the next one due first
+    pub async fn get_recurring_transactions(
+        &mut self,
+    ) -> Result<Vec<RecurringTransaction>, DbError> {
+        Ok(sqlx::query_as::<_, RecurringTransaction>(
+            r#"
+            SELECT
+                recurring_transactions.*,
+                categories.name AS category
+            FROM
+                recurring_transactions
+                LEFT JOIN categories ON categories.id = recurring_transactions.category_id
+            ORDER BY
+                next_date,
+                title
+            "#,
+        )
+        .fetch_all(&mut self.conn)
+        .await?)
+    }
+
+    pub async fn add_recurring_transaction(
+        &mut self,
+        recurring: &RecurringTransaction,
+    ) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT INTO recurring_transactions
+                (title, amount, details, kind, category_id, recurrence, next_date, account_id)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&recurring.title)
+        .bind(recurring.amount)
+        .bind(&recurring.details)
+        .bind(recurring.kind)
+        .bind(recurring.category_id)
+        .bind(&recurring.recurrence)
+        .bind(recurring.next_date)
+        .bind(recurring.account_id)
+        .execute(&mut self.conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Stop a transaction recurring, occurrences already added are kept
+    pub async fn delete_recurring_transaction(&mut self, id: i64) -> Result<(), DbError> {
+        sqlx::query("DELETE FROM recurring_transactions WHERE id = ?")
+            .bind(id)
+            .execute(&mut self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Add a transaction for every occurrence due on or before `today` and move
+    /// each recurring transaction on to its next date, returning how many
+    /// transactions were added
+    pub async fn add_due_recurring_transactions(
+        &mut self,
+        today: NaiveDate,
+    ) -> Result<u64, DbError> {
+        let recurring = self.get_recurring_transactions().await?;
+        let mut tx = self.conn.begin().await?;
+        let mut added = 0;
+        for r in recurring.iter().filter(|r| r.next_date <= today) {
+            let mut date = r.next_date;
+            while date <= today {
+                let transaction = r.occurrence(date);
+                sqlx::query(INSERT_TRANSACTION)
+                    .bind(&transaction.title)
+                    .bind(transaction.amount)
+                    .bind(&transaction.details)
+                    .bind(transaction.date)
+                    .bind(transaction.kind)
+                    .bind(transaction.category_id)
+                    .bind(transaction.account_id)
+                    .bind(&transaction.currency)
+                    .execute(&mut tx)
+                    .await?;
+                added += 1;
+                date = r.recurrence.next_after(date);
+            }
+            sqlx::query("UPDATE recurring_transactions SET next_date = ? WHERE id = ?")
+                .bind(date)
+                .bind(r.id)
+                .execute(&mut tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(added)
+    }
+
+    /// Budgets in effect for a month (`YYYY-MM`), the overall budget first
+    ///
+    /// Each category uses its latest budget from that month or before, one set
+    /// to zero means the category no longer has a budget
+    pub async fn get_budgets(&mut self, month: &str) -> Result<Vec<Budget>, DbError> {
+        Ok(sqlx::query_as::<_, Budget>(
+            r#"
+            SELECT
+                budgets.category_id,
+                budgets.amount,
+                categories.name AS category
+            FROM
+                budgets
+                LEFT JOIN categories ON categories.id = budgets.category_id
+            WHERE
+                budgets.amount > 0
+                AND budgets.month = (
+                    SELECT
+                        MAX(latest.month)
+                    FROM
+                        budgets AS latest
+                    WHERE
+                        latest.month <= ?
+                        AND latest.category_id IS budgets.category_id
+                )
+            ORDER BY
+                budgets.category_id IS NOT NULL,
+                categories.name
+            "#,
+        )
+        .bind(month)
+        .fetch_all(&mut self.conn)
+        .await?)
+    }
+
+    /// Set the budget for a category (or overall) from a month (`YYYY-MM`),
+    /// replacing any already set for that month
+    pub async fn set_budget(
+        &mut self,
+        category_id: Option<i64>,
+        month: &str,
+        amount: Money,
+    ) -> Result<(), DbError> {
+        let updated =
+            sqlx::query("UPDATE budgets SET amount = ? WHERE month = ? AND category_id IS ?")
+                .bind(amount)
+                .bind(month)
+                .bind(category_id)
+                .execute(&mut self.conn)
+                .await?;
+        if updated.rows_affected() == 0 {
+            sqlx::query("INSERT INTO budgets (category_id, month, amount) VALUES (?, ?, ?)")
+                .bind(category_id)
+                .bind(month)
+                .bind(amount)
+                .execute(&mut self.conn)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Total spent in each category in a local month (`YYYY-MM`), income isn't
+    /// counted
+    ///
+    /// Dates are stored in UTC so they are converted to local time to match the
+    /// month budgets are shown for
+    pub async fn get_monthly_spending(
+        &mut self,
+        month: &str,
+    ) -> Result<HashMap<Option<i64>, Money>, DbError> {
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT
+                category_id,
+                SUM({}) AS spent
+            FROM
+                transactions
+            WHERE
+                kind = 'expense'
+                AND transfer_id IS NULL
+                AND strftime('%Y-%m', date, 'localtime') = ?
+            GROUP BY
+                category_id
+            "#,
+            BASE_AMOUNT
+        ))
+        .bind(month)
+        .fetch_all(&mut self.conn)
+        .await?;
+        let mut spending = HashMap::with_capacity(rows.len());
+        for row in rows {
+            spending.insert(row.try_get("category_id")?, row.try_get("spent")?);
+        }
+        Ok(spending)
+    }
+
+    pub async fn get_all_movies(&mut self) -> Result<Vec<MoneyTransaction>, DbError> {
+        Ok(sqlx::query_as::<_, MoneyTransaction>(
             "SELECT * FROM transactions ORDER BY julianday(date) DESC",
         )
         .fetch_all(&mut self.conn)
-        .await
-        .unwrap()
+        .await?)
+    }
+
+    /// Modules by year then term, in the order they were added within those,
+    /// each with its assessments
+    pub async fn get_modules(&mut self) -> Result<Vec<Module>, DbError> {
+        let rows = sqlx::query("SELECT * FROM modules ORDER BY year, term, position")
+            .fetch_all(&mut self.conn)
+            .await?;
+        let mut grades: HashMap<i64, Vec<Grade>> = HashMap::new();
+        for grade in sqlx::query_as::<_, Grade>("SELECT * FROM assessments ORDER BY id")
+            .fetch_all(&mut self.conn)
+            .await?
+        {
+            grades.entry(grade.module_id).or_default().push(grade);
+        }
+        let mut modules = Vec::with_capacity(rows.len());
+        for row in rows {
+            let id: i64 = row.try_get("id")?;
+            modules.push(Module {
+                id,
+                name: row.try_get("name")?,
+                year: row.try_get("year")?,
+                term: row.try_get("term")?,
+                credits: row.try_get("credits")?,
+                grades: grades.remove(&id).unwrap_or_default(),
+            });
+        }
+        Ok(modules)
+    }
+
+    /// Add a module after the others, returning its id
+    pub async fn add_module(&mut self, module: &Module) -> Result<i64, DbError> {
+        Ok(sqlx::query(INSERT_MODULE)
+            .bind(&module.name)
+            .bind(module.year)
+            .bind(&module.term)
+            .bind(module.credits)
+            .execute(&mut self.conn)
+            .await?
+            .last_insert_rowid())
+    }
+
+    /// Save a module's name, year, term and credits
+    pub async fn update_module(&mut self, module: &Module) -> Result<(), DbError> {
+        sqlx::query("UPDATE modules SET name = ?, year = ?, term = ?, credits = ? WHERE id = ?")
+            .bind(&module.name)
+            .bind(module.year)
+            .bind(&module.term)
+            .bind(module.credits)
+            .bind(module.id)
+            .execute(&mut self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Delete a module along with its assessments
+    pub async fn delete_module(&mut self, id: i64) -> Result<(), DbError> {
+        sqlx::query("DELETE FROM modules WHERE id = ?")
+            .bind(id)
+            .execute(&mut self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Add an assessment to its module, returning its id
+    pub async fn add_grade(&mut self, grade: &Grade) -> Result<i64, DbError> {
+        Ok(sqlx::query(
+            r#"
+            INSERT INTO assessments (module_id, name, percentage, weight, due_date)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(grade.module_id)
+        .bind(&grade.name)
+        .bind(grade.percentage)
+        .bind(grade.weight)
+        .bind(grade.due_date)
+        .execute(&mut self.conn)
+        .await?
+        .last_insert_rowid())
+    }
+
+    /// Save an assessment's name, mark, weight and due date
+    pub async fn update_grade(&mut self, grade: &Grade) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            UPDATE assessments
+            SET name = ?, percentage = ?, weight = ?, due_date = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(&grade.name)
+        .bind(grade.percentage)
+        .bind(grade.weight)
+        .bind(grade.due_date)
+        .bind(grade.id)
+        .execute(&mut self.conn)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_grade(&mut self, id: i64) -> Result<(), DbError> {
+        sqlx::query("DELETE FROM assessments WHERE id = ?")
+            .bind(id)
+            .execute(&mut self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Add modules with all their assessments, either all are added or none
+    pub async fn import_modules(&mut self, modules: &[Module]) -> Result<(), DbError> {
+        let mut tx = self.conn.begin().await?;
+        for module in modules {
+            let module_id = sqlx::query(INSERT_MODULE)
+                .bind(&module.name)
+                .bind(module.year)
+                .bind(&module.term)
+                .bind(module.credits)
+                .execute(&mut tx)
+                .await?
+                .last_insert_rowid();
+            for grade in &module.grades {
+                sqlx::query(
+                    r#"
+                    INSERT INTO assessments (module_id, name, percentage, weight, due_date)
+                    VALUES (?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(module_id)
+                .bind(&grade.name)
+                .bind(grade.percentage)
+                .bind(grade.weight)
+                .bind(grade.due_date)
+                .execute(&mut tx)
+                .await?;
+            }
+        }
+        tx.commit().await?;
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use chrono::Local;
-    use sqlx::Row;
+    use chrono::TimeZone;
 
     use super::*;
+    use crate::category_rules::RuleKind;
+
+    async fn test_db() -> DB {
+        let mut db = DB::connect("sqlite::memory:").await.unwrap();
+        db.run_migrations().await.unwrap();
+        for (title, amount, details, day) in [
+            ("test shop", 500, "it's 50% off", 1),
+            ("100% juice", 250, "", 1),
+            ("o'brien's", 2000, "under_score", 2),
+            ("test_two", 5000, "", 3),
+        ] {
+            db.add_transaction(&MoneyTransaction::new(
+                title.to_owned(),
+                Money::from_minor(amount),
+                details.to_owned(),
+                Utc.with_ymd_and_hms(2023, 7, day, 12, 0, 0).unwrap(),
+                TransactionKind::Expense,
+                None,
+            ))
+            .await
+            .unwrap();
+        }
+        db
+    }
+
+    #[tokio::test]
+    async fn test_todo_order() {
+        let mut db = test_db().await;
+        for description in ["first", "second", "third"] {
+            db.add_todo(&Todo::new(description.to_owned(), None, None))
+                .await
+                .unwrap();
+        }
+        let todos = db.get_todos().await.unwrap();
+        let completed = Utc.with_ymd_and_hms(2023, 7, 1, 12, 0, 0).unwrap();
+        db.complete_todo(todos[1].id, completed + Duration::days(1), None)
+            .await
+            .unwrap();
+        db.complete_todo(todos[0].id, completed, None)
+            .await
+            .unwrap();
+
+        let todos = db.get_todos().await.unwrap();
+        let descriptions: Vec<&str> = todos.iter().map(|t| t.description.as_str()).collect();
+        assert_eq!(descriptions, vec!["third", "second", "first"]);
+    }
+
+    #[tokio::test]
+    async fn test_recurring_todo_completion() {
+        let mut db = test_db().await;
+        let today = NaiveDate::from_ymd_opt(2023, 7, 1).unwrap();
+        db.add_todo(&Todo::new(
+            "bins".to_owned(),
+            Some(Recurrence::Daily),
+            Some(today),
+        ))
+        .await
+        .unwrap();
+        let todo = db.get_todos().await.unwrap().remove(0);
+        let next = todo.next_occurrence(today);
+        let completed = Utc.with_ymd_and_hms(2023, 7, 1, 12, 0, 0).unwrap();
+
+        // moving it back takes away the next occurrence, so completing it
+        // again doesn't leave a second copy
+        db.complete_todo(todo.id, completed, next.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(db.get_todos().await.unwrap().len(), 2);
+        db.uncomplete_todo(todo.id).await.unwrap();
+        assert_eq!(db.get_todos().await.unwrap().len(), 1);
+        db.complete_todo(todo.id, completed, next.as_ref())
+            .await
+            .unwrap();
+        let todos = db.get_todos().await.unwrap();
+        assert_eq!(todos.len(), 2);
+        assert_eq!(todos[0].due_date, today.succ_opt());
+
+        // once the next occurrence is done too it is kept and not added again
+        db.complete_todo(todos[0].id, completed, None)
+            .await
+            .unwrap();
+        db.uncomplete_todo(todo.id).await.unwrap();
+        db.complete_todo(todo.id, completed, next.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(db.get_todos().await.unwrap().len(), 2);
+    }
 
     #[tokio::test]
     async fn test_transaction() {
-        let mut db = DB::new().await;
-        //         db.add_transaction(&Transaction {
-        //             id: -1,
-        //             title: "new".to_owned(),
-        //             amount: 10.0,
-        //             date: Utc::now(),
-        //         })
-        //         .await;
-        //         let a = db.get_transactions_by_keyword("test").await;
-        //         println!("{:?}", a);
-        let keyword = "test";
-        let min = 0;
-        let max = 10000;
-        let a = sqlx::query(&format!(
-            r#"
-            SELECT
-                COUNT(*) as count
-            FROM
-                transactions
-            WHERE
-                title LIKE '%{}%'
-                AND amount >= {}
-                AND amount <= {}
-            "#,
-            keyword, min, max
+        let mut db = test_db().await;
+        let mut filter = TransactionFilter::new();
+        assert_eq!(db.get_num_of_transactions(&filter).await.unwrap(), 4);
+
+        filter.keyword = "test".to_owned();
+        filter.min = Money::from_minor(1000);
+        let transactions = db.query_transactions(&filter, None, 10).await.unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].title, "test_two");
+    }
+
+    #[tokio::test]
+    async fn test_transaction_filter_escaping() {
+        let mut db = test_db().await;
+        let mut filter = TransactionFilter::new();
+
+        filter.keyword = "o'brien".to_owned();
+        assert_eq!(db.get_num_of_transactions(&filter).await.unwrap(), 1);
+
+        filter.keyword = "%".to_owned();
+        assert_eq!(db.get_num_of_transactions(&filter).await.unwrap(), 1);
+
+        filter.keyword = "_".to_owned();
+        assert_eq!(db.get_num_of_transactions(&filter).await.unwrap(), 1);
+
+        filter.keyword = "".to_owned();
+        filter.details = "_".to_owned();
+        assert_eq!(db.get_num_of_transactions(&filter).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_filter_dates_and_sort() {
+        let mut db = test_db().await;
+        let mut filter = TransactionFilter::new();
+        filter.start_date = Some(Utc.with_ymd_and_hms(2023, 7, 2, 0, 0, 0).unwrap());
+        filter.end_date = Some(Utc.with_ymd_and_hms(2023, 7, 3, 0, 0, 0).unwrap());
+        let transactions = db.query_transactions(&filter, None, 10).await.unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].title, "o'brien's");
+
+        let mut filter = TransactionFilter::new();
+        filter.sort = TransactionSort::AmountAsc;
+        let transactions = db.query_transactions(&filter, None, 10).await.unwrap();
+        let cursor = TransactionCursor::at(&transactions[1]);
+        let transactions = db
+            .query_transactions(&filter, Some(&cursor), 2)
+            .await
+            .unwrap();
+        let titles: Vec<&str> = transactions.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["test shop", "o'brien's"]);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_cursors() {
+        let mut db = test_db().await;
+        // a second transaction at the same time as "test shop" so only the id
+        // tells them apart
+        db.add_transaction(&MoneyTransaction::new(
+            "same time".to_owned(),
+            Money::from_minor(500),
+            "".to_owned(),
+            Utc.with_ymd_and_hms(2023, 7, 1, 12, 0, 0).unwrap(),
+            TransactionKind::Expense,
+            None,
+        ))
+        .await
+        .unwrap();
+
+        for sort in [
+            TransactionSort::DateDesc,
+            TransactionSort::DateAsc,
+            TransactionSort::AmountDesc,
+            TransactionSort::AmountAsc,
+        ] {
+            let mut filter = TransactionFilter::new();
+            filter.sort = sort;
+            let all = db.query_transactions(&filter, None, 10).await.unwrap();
+            assert_eq!(all.len(), 5);
+            // a cursor at each transaction starts a page with it
+            for (i, expected) in all.iter().enumerate() {
+                let cursor = TransactionCursor::at(expected);
+                let page = db
+                    .query_transactions(&filter, Some(&cursor), 1)
+                    .await
+                    .unwrap();
+                assert_eq!(page[0].id, expected.id, "{:?}", sort);
+                assert_eq!(
+                    db.get_num_of_transactions_before(&filter, &cursor)
+                        .await
+                        .unwrap(),
+                    i as u32
+                );
+            }
+            let before = db
+                .query_transactions_before(&filter, None, 10)
+                .await
+                .unwrap();
+            let reversed: Vec<i64> = all.iter().rev().map(|t| t.id).collect();
+            assert_eq!(before.iter().map(|t| t.id).collect::<Vec<i64>>(), reversed);
+            let before = db
+                .query_transactions_before(&filter, Some(&TransactionCursor::at(&all[2])), 10)
+                .await
+                .unwrap();
+            assert_eq!(
+                before.iter().map(|t| t.id).collect::<Vec<i64>>(),
+                vec![all[1].id, all[0].id]
+            );
+        }
+
+        // jumping to a date works for both date orders
+        let day = NaiveDate::from_ymd_opt(2023, 7, 2).unwrap();
+        let mut filter = TransactionFilter::new();
+        let cursor = TransactionCursor::on_date(day, filter.sort);
+        let page = db
+            .query_transactions(&filter, Some(&cursor), 1)
+            .await
+            .unwrap();
+        assert_eq!(page[0].title, "o'brien's");
+        filter.sort = TransactionSort::DateAsc;
+        let cursor = TransactionCursor::on_date(day, filter.sort);
+        let page = db
+            .query_transactions(&filter, Some(&cursor), 1)
+            .await
+            .unwrap();
+        assert_eq!(page[0].title, "o'brien's");
+    }
+
+    #[tokio::test]
+    async fn test_budgets() {
+        let mut db = test_db().await;
+        let groceries = db.get_or_create_category("Groceries").await.unwrap();
+        db.set_budget(None, "2023-06", Money::from_minor(10000))
+            .await
+            .unwrap();
+        db.set_budget(Some(groceries), "2023-06", Money::from_minor(2000))
+            .await
+            .unwrap();
+        db.set_budget(Some(groceries), "2023-07", Money::from_minor(3000))
+            .await
+            .unwrap();
+
+        // the overall budget carries on into July, groceries is replaced
+        let budgets = db.get_budgets("2023-07").await.unwrap();
+        let amounts: Vec<(Option<i64>, i64)> = budgets
+            .iter()
+            .map(|b| (b.category_id, b.amount.minor()))
+            .collect();
+        assert_eq!(amounts, vec![(None, 10000), (Some(groceries), 3000)]);
+
+        // setting a budget again for the same month replaces it, zero removes it
+        db.set_budget(Some(groceries), "2023-07", Money::from_minor(0))
+            .await
+            .unwrap();
+        assert_eq!(db.get_budgets("2023-07").await.unwrap().len(), 1);
+        assert_eq!(db.get_budgets("2023-06").await.unwrap().len(), 2);
+
+        let spending = db.get_monthly_spending("2023-07").await.unwrap();
+        assert_eq!(spending.get(&None), Some(&Money::from_minor(7750)));
+        assert!(db.get_monthly_spending("2023-06").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_spending_stats() {
+        let mut db = test_db().await;
+        let date = |m, d| NaiveDate::from_ymd_opt(2023, m, d).unwrap();
+        let juice = db.get_or_create_category("Juice").await.unwrap();
+        db.add_transaction(&MoneyTransaction::new(
+            "june juice".to_owned(),
+            Money::from_minor(100),
+            "".to_owned(),
+            Utc.with_ymd_and_hms(2023, 6, 30, 12, 0, 0).unwrap(),
+            TransactionKind::Expense,
+            Some(juice),
+        ))
+        .await
+        .unwrap();
+
+        let daily = db
+            .get_spending_totals(StatsPeriod::Day, date(7, 1), date(8, 1))
+            .await
+            .unwrap();
+        let daily: Vec<(NaiveDate, i64)> = daily.iter().map(|(d, m)| (*d, m.minor())).collect();
+        assert_eq!(
+            daily,
+            vec![(date(7, 1), 750), (date(7, 2), 2000), (date(7, 3), 5000)]
+        );
+
+        // 2023-06-26 is the Monday before the first of July
+        let weekly = db
+            .get_spending_totals(StatsPeriod::Week, date(6, 1), date(8, 1))
+            .await
+            .unwrap();
+        assert_eq!(
+            weekly,
+            vec![
+                (date(6, 26), Money::from_minor(2850)),
+                (date(7, 3), Money::from_minor(5000))
+            ]
+        );
+        assert_eq!(StatsPeriod::Week.start_of(date(7, 2)), date(6, 26));
+
+        let monthly = db
+            .get_spending_totals(StatsPeriod::Month, date(6, 1), date(8, 1))
+            .await
+            .unwrap();
+        assert_eq!(
+            monthly,
+            vec![
+                (date(6, 1), Money::from_minor(100)),
+                (date(7, 1), Money::from_minor(7750))
+            ]
+        );
+
+        assert_eq!(
+            db.get_spending_between(date(7, 2), date(7, 3))
+                .await
+                .unwrap(),
+            Money::from_minor(2000)
+        );
+        let top = db
+            .get_top_categories(date(6, 1), date(8, 1), 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            top,
+            vec![("Uncategorised".to_owned(), Money::from_minor(7750))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bulk_import() {
+        let mut db = test_db().await;
+        let existing = MoneyTransaction::new(
+            "test shop".to_owned(),
+            Money::from_minor(500),
+            "".to_owned(),
+            Utc.with_ymd_and_hms(2023, 7, 1, 0, 0, 0).unwrap(),
+            TransactionKind::Expense,
+            None,
+        );
+        assert!(db.is_duplicate_transaction(&existing).await.unwrap());
+        let mut income = existing.clone();
+        income.kind = TransactionKind::Income;
+        assert!(!db.is_duplicate_transaction(&income).await.unwrap());
+
+        db.add_transactions(&[income.clone(), income])
+            .await
+            .unwrap();
+        let filter = TransactionFilter::new();
+        assert_eq!(db.get_num_of_transactions(&filter).await.unwrap(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_apply_category_rules() {
+        let mut db = test_db().await;
+        let juice = db.get_or_create_category("Juice").await.unwrap();
+        let shops = db.get_or_create_category("Shops").await.unwrap();
+        db.add_category_rule(&CategoryRule::new(
+            RuleKind::Contains,
+            "juice".to_owned(),
+            None,
+            None,
+            juice,
+        ))
+        .await
+        .unwrap();
+        db.add_category_rule(&CategoryRule::new(
+            RuleKind::Regex,
+            "^test".to_owned(),
+            Some(Money::from_minor(1000)),
+            None,
+            shops,
+        ))
+        .await
+        .unwrap();
+        let rules = db.get_category_rules().await.unwrap();
+        assert_eq!(rules[0].category.as_deref(), Some("Juice"));
+        assert!(rules[0].position < rules[1].position);
+
+        assert_eq!(db.apply_category_rules().await.unwrap(), 2);
+        // nothing left to change
+        assert_eq!(db.apply_category_rules().await.unwrap(), 0);
+        let categories: Vec<(String, Option<String>)> = db
+            .get_all_transactions()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.title, t.category))
+            .collect();
+        assert!(categories.contains(&("100% juice".to_owned(), Some("Juice".to_owned()))));
+        assert!(categories.contains(&("test_two".to_owned(), Some("Shops".to_owned()))));
+        // under the minimum amount
+        assert!(categories.contains(&("test shop".to_owned(), None)));
+    }
+
+    #[tokio::test]
+    async fn test_recurring_transactions() {
+        let mut db = test_db().await;
+        let date = |d| NaiveDate::from_ymd_opt(2023, 8, d).unwrap();
+        db.add_recurring_transaction(&RecurringTransaction::new(
+            "Rent".to_owned(),
+            Money::from_minor(80000),
+            "".to_owned(),
+            TransactionKind::Expense,
+            None,
+            Recurrence::Monthly(1),
+            date(1),
         ))
-        .fetch_all(&mut db.conn)
         .await
         .unwrap();
-        let b: u32 = a.get(0).unwrap().try_get("count").unwrap();
-        println!("{:?}", b);
+        db.add_recurring_transaction(&RecurringTransaction::new(
+            "Gym".to_owned(),
+            Money::from_minor(500),
+            "".to_owned(),
+            TransactionKind::Expense,
+            None,
+            Recurrence::EveryNDays(7),
+            date(1),
+        ))
+        .await
+        .unwrap();
+        let before = db.get_all_transactions().await.unwrap().len();
+
+        // rent once, gym on the 1st, 8th and 15th
+        assert_eq!(
+            db.add_due_recurring_transactions(date(20)).await.unwrap(),
+            4
+        );
+        assert_eq!(
+            db.add_due_recurring_transactions(date(20)).await.unwrap(),
+            0
+        );
+        assert_eq!(db.get_all_transactions().await.unwrap().len(), before + 4);
+
+        let recurring = db.get_recurring_transactions().await.unwrap();
+        assert_eq!(recurring[0].title, "Gym");
+        assert_eq!(recurring[0].next_date, date(22));
+        assert_eq!(
+            recurring[1].next_date,
+            NaiveDate::from_ymd_opt(2023, 9, 1).unwrap()
+        );
+        assert_eq!(recurring[1].monthly_amount(), Money::from_minor(-80000));
+    }
+
+    #[tokio::test]
+    async fn test_accounts_and_transfers() {
+        let mut db = test_db().await;
+        db.save_account("Current", Money::from_minor(10000))
+            .await
+            .unwrap();
+        db.save_account("Savings", Money::from_minor(50000))
+            .await
+            .unwrap();
+        let accounts = db.get_accounts().await.unwrap();
+        assert_eq!(accounts.len(), 2);
+        // opening balance minus the test transactions
+        assert_eq!(accounts[0].balance, Money::from_minor(10000 - 7750));
+        assert_eq!(accounts[1].balance, Money::from_minor(50000));
+
+        let date = Utc.with_ymd_and_hms(2023, 7, 4, 12, 0, 0).unwrap();
+        db.add_transfer(
+            &accounts[0],
+            &accounts[1],
+            Money::from_minor(1000),
+            date,
+            "",
+        )
+        .await
+        .unwrap();
+        let accounts = db.get_accounts().await.unwrap();
+        assert_eq!(accounts[0].balance, Money::from_minor(10000 - 8750));
+        assert_eq!(accounts[1].balance, Money::from_minor(51000));
+        // moving money isn't spending
+        let day = NaiveDate::from_ymd_opt(2023, 7, 4).unwrap();
+        assert_eq!(
+            db.get_spending_between(day, day + Duration::days(1))
+                .await
+                .unwrap(),
+            Money::default()
+        );
+        let balances = db.get_daily_balances(Some(accounts[1].id)).await.unwrap();
+        assert_eq!(
+            balances,
+            vec![(day, Money::from_minor(1000), Money::from_minor(51000))]
+        );
+
+        let mut filter = TransactionFilter::new();
+        filter.account_id = Some(accounts[1].id);
+        let savings = db.query_transactions(&filter, None, 10).await.unwrap();
+        assert_eq!(savings.len(), 1);
+        assert_eq!(savings[0].title, "Transfer from Current");
+
+        // editing one half changes the other, deleting one deletes both
+        let mut transfer = savings[0].clone();
+        transfer.amount = Money::from_minor(2000);
+        db.update_transaction(&transfer).await.unwrap();
+        let accounts = db.get_accounts().await.unwrap();
+        assert_eq!(accounts[0].balance, Money::from_minor(10000 - 9750));
+
+        // changing the direction or currency of one half changes the other too
+        transfer.kind = TransactionKind::Expense;
+        transfer.currency = Some("USD".to_owned());
+        db.update_transaction(&transfer).await.unwrap();
+        filter.account_id = Some(accounts[0].id);
+        let current = db.query_transactions(&filter, None, 10).await.unwrap();
+        let other = current
+            .iter()
+            .find(|t| t.transfer_id == Some(transfer.id))
+            .unwrap();
+        assert_eq!(other.kind, TransactionKind::Income);
+        assert_eq!(other.currency.as_deref(), Some("USD"));
+        assert_eq!(other.amount, Money::from_minor(2000));
+        db.delete_transaction(transfer.id).await.unwrap();
+        assert_eq!(
+            db.get_num_of_transactions(&TransactionFilter::new())
+                .await
+                .unwrap(),
+            4
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exchange_rates() {
+        let mut db = test_db().await;
+        let day = |d| NaiveDate::from_ymd_opt(2023, 7, d).unwrap();
+        db.save_exchange_rates(&[
+            ExchangeRate {
+                currency: "USD".to_owned(),
+                date: day(2),
+                rate: 0.8,
+            },
+            ExchangeRate {
+                currency: "USD".to_owned(),
+                date: day(10),
+                rate: 0.75,
+            },
+        ])
+        .await
+        .unwrap();
+        for d in [1, 5, 12] {
+            let mut transaction = MoneyTransaction::new(
+                "abroad".to_owned(),
+                Money::from_minor(1000),
+                "".to_owned(),
+                Utc.with_ymd_and_hms(2023, 7, d, 12, 0, 0).unwrap(),
+                TransactionKind::Expense,
+                None,
+            );
+            transaction.currency = Some("USD".to_owned());
+            db.add_transaction(&transaction).await.unwrap();
+        }
+        // before the first rate uses the first rate, then each uses the rate
+        // in effect on its day
+        for (start, end, total) in [(1, 2, 500 + 250 + 800), (5, 6, 800), (12, 13, 750)] {
+            assert_eq!(
+                db.get_spending_between(day(start), day(end)).await.unwrap(),
+                Money::from_minor(total)
+            );
+        }
+        let accounts = db.get_accounts().await.unwrap();
+        assert_eq!(accounts[0].balance, Money::from_minor(-7750 - 2350));
+
+        // replacing a rate changes the totals
+        db.save_exchange_rates(&[ExchangeRate {
+            currency: "USD".to_owned(),
+            date: day(10),
+            rate: 0.5,
+        }])
+        .await
+        .unwrap();
+        let rates = db.get_exchange_rates().await.unwrap();
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].rate, 0.5);
+        assert_eq!(
+            db.get_spending_between(day(12), day(13)).await.unwrap(),
+            Money::from_minor(500)
+        );
+
+        // a currency without rates is left out of totals
+        let mut transaction = MoneyTransaction::new(
+            "no rate".to_owned(),
+            Money::from_minor(1000),
+            "".to_owned(),
+            Utc.with_ymd_and_hms(2023, 7, 12, 12, 0, 0).unwrap(),
+            TransactionKind::Expense,
+            None,
+        );
+        transaction.currency = Some("JPY".to_owned());
+        db.add_transaction(&transaction).await.unwrap();
+        assert_eq!(
+            db.get_spending_between(day(12), day(13)).await.unwrap(),
+            Money::from_minor(500)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_attachments() {
+        let mut db = test_db().await;
+        let transactions = db
+            .query_transactions(&TransactionFilter::new(), None, 2)
+            .await
+            .unwrap();
+        let (first, second) = (transactions[0].id, transactions[1].id);
+        db.add_attachment(first, "/receipts/a.pdf").await.unwrap();
+        db.add_attachment(first, "/receipts/b.jpg").await.unwrap();
+        db.add_attachment(second, "/receipts/c.pdf").await.unwrap();
+        let paths = db.get_attachment_paths().await.unwrap();
+        assert_eq!(paths[&first], vec!["/receipts/a.pdf", "/receipts/b.jpg"]);
+
+        let attachments = db.get_attachments(first).await.unwrap();
+        db.delete_attachment(attachments[0].id).await.unwrap();
+        // deleting the transaction removes the rest of its attachments
+        db.delete_transaction(second).await.unwrap();
+        let paths = db.get_attachment_paths().await.unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[&first], vec!["/receipts/b.jpg"]);
+    }
+
+    #[tokio::test]
+    async fn test_modules() {
+        let mut db = test_db().await;
+        let mut grade = Grade::new("Quiz".to_owned(), Some(57.5), 15.);
+        let mut mobile = Module::new("Mobile Devices".to_owned(), 3);
+        mobile.grades = vec![
+            grade.clone(),
+            Grade::new("Project".to_owned(), Some(77.), 85.),
+        ];
+        db.import_modules(&[mobile, Module::new("Graphics".to_owned(), 3)])
+            .await
+            .unwrap();
+        let modules = db.get_modules().await.unwrap();
+        let names: Vec<&str> = modules.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["Mobile Devices", "Graphics"]);
+        assert_eq!(modules[0].grades.len(), 2);
+        assert_eq!(modules[0].grades[0].percentage, Some(57.5));
+        assert_eq!(modules[0].mark(), Some(74.075));
+        assert_eq!(modules[1].mark(), None);
+
+        grade.module_id = modules[1].id;
+        db.add_grade(&grade).await.unwrap();
+        let id = db
+            .add_module(&Module::new("Law".to_owned(), 3))
+            .await
+            .unwrap();
+        let modules = db.get_modules().await.unwrap();
+        assert_eq!(modules[1].grades[0].name, "Quiz");
+        assert_eq!(modules[2].id, id);
+
+        // earlier years come first
+        let mut law = modules[2].clone();
+        law.year = 1;
+        law.term = Some("Autumn".to_owned());
+        law.credits = 10;
+        db.update_module(&law).await.unwrap();
+        let modules = db.get_modules().await.unwrap();
+        assert_eq!(modules[0].name, "Law");
+        assert_eq!(modules[0].term.as_deref(), Some("Autumn"));
+        assert_eq!(modules[0].credits, 10);
+
+        let mut quiz = modules[2].grades[0].clone();
+        quiz.percentage = None;
+        quiz.due_date = NaiveDate::from_ymd_opt(2024, 5, 10);
+        db.update_grade(&quiz).await.unwrap();
+        db.delete_grade(modules[1].grades[1].id).await.unwrap();
+        // a module's assessments go with it
+        db.delete_module(modules[0].id).await.unwrap();
+        let modules = db.get_modules().await.unwrap();
+        assert_eq!(modules.len(), 2);
+        assert_eq!(modules[0].grades.len(), 1);
+        assert_eq!(modules[1].grades[0].percentage, None);
+        assert_eq!(
+            modules[1].grades[0].due_date,
+            NaiveDate::from_ymd_opt(2024, 5, 10)
+        );
+        // only marked work counts
+        assert_eq!(modules[1].mark(), None);
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM assessments")
+            .fetch_one(&mut db.conn)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
     }
 }
@@ -0,0 +1,285 @@
+use chrono::Utc;
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::Span,
+    widgets::{BorderType, Borders, StatefulWidget},
+};
+
+use crate::{
+    config::CONFIG,
+    db::{DbError, ExchangeRate, DB},
+    form::{render_form_popup, DateField, FormFieldStyle, FormState, FormValue, TextField},
+    importer,
+    styles::AppStyles,
+    util::{centered_rect, draw_rect_borders, generic_increment},
+};
+
+#[derive(Debug)]
+pub struct ExchangeRatesState {
+    pub rates: Vec<ExchangeRate>,
+    pub selected: u32,
+    pub show_form: bool,
+    pub form_state: FormState,
+    pub show_import_form: bool,
+    pub import_form: FormState,
+    /// Shown under the list, e.g. how many rates were imported
+    message: Option<String>,
+    /// Shown in the open form when it can't be submitted
+    form_error: Option<String>,
+}
+
+impl ExchangeRatesState {
+    pub async fn new(db: &mut DB) -> Result<ExchangeRatesState, DbError> {
+        let mut form_state = FormState::new();
+        form_state.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            true,
+            FormFieldStyle::new("Currency code, e.g. USD".to_owned()),
+        )));
+        form_state.add_field(Box::new(DateField::new(
+            Utc::now(),
+            true,
+            FormFieldStyle::new("From date".to_owned()),
+        )));
+        form_state.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            true,
+            FormFieldStyle::new(format!("Value of 1 unit in {}", CONFIG.currency.code)),
+        )));
+        let mut import_form = FormState::new();
+        import_form.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            true,
+            FormFieldStyle::new("CSV file of currency,date,rate".to_owned()),
+        )));
+        let mut state = ExchangeRatesState {
+            rates: Vec::new(),
+            selected: 0,
+            show_form: false,
+            form_state,
+            show_import_form: false,
+            import_form,
+            message: None,
+            form_error: None,
+        };
+        state.refresh(db).await?;
+        Ok(state)
+    }
+
+    pub async fn refresh(&mut self, db: &mut DB) -> Result<(), DbError> {
+        self.rates = db.get_exchange_rates().await?;
+        if self.selected as usize >= self.rates.len() {
+            self.selected = self.rates.len().saturating_sub(1) as u32;
+        }
+        Ok(())
+    }
+
+    pub fn increment_selected(&mut self, amount: i32) {
+        if self.rates.is_empty() {
+            return;
+        }
+        generic_increment(&mut self.selected, 0, self.rates.len() as u32 - 1, amount);
+    }
+
+    /// The form currently taking input
+    pub fn get_active_form_mut(&mut self) -> &mut FormState {
+        if self.show_import_form {
+            &mut self.import_form
+        } else {
+            &mut self.form_state
+        }
+    }
+
+    pub fn toggle_form(&mut self) {
+        self.show_form = !self.show_form;
+        self.form_error = None;
+        if !self.show_form {
+            self.form_state.reset_fields();
+        }
+    }
+
+    pub fn toggle_import_form(&mut self) {
+        self.show_import_form = !self.show_import_form;
+        self.form_error = None;
+        if !self.show_import_form {
+            self.import_form.reset_fields();
+        }
+    }
+
+    pub async fn submit_form(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let fields = self.form_state.get_fields();
+        let vals: Vec<&FormValue> = fields.iter().map(|f| f.get_internal_value()).collect();
+        if let [currency, date, rate] = vals.as_slice() {
+            let currency =
+                match importer::parse_currency_code(currency.try_get_text_value().unwrap()) {
+                    Some(code) if code != CONFIG.currency.code => code,
+                    Some(_) => {
+                        self.form_error = Some("That is the base currency".to_owned());
+                        return Ok(());
+                    }
+                    None => {
+                        self.form_error = Some("Use a three letter currency code".to_owned());
+                        return Ok(());
+                    }
+                };
+            let rate = match rate.try_get_text_value().unwrap().trim().parse::<f64>() {
+                Ok(rate) if rate > 0. => rate,
+                _ => {
+                    self.form_error = Some("Enter a rate above 0".to_owned());
+                    return Ok(());
+                }
+            };
+            db.save_exchange_rates(&[ExchangeRate {
+                currency,
+                date: date.try_get_date_value().unwrap().date_naive(),
+                rate,
+            }])
+            .await?;
+            self.toggle_form();
+            self.refresh(db).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn submit_import_form(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let path = self.import_form.get_fields()[0]
+            .get_internal_value()
+            .try_get_text_value()
+            .unwrap()
+            .clone();
+        let rates = match importer::import_exchange_rates(&path) {
+            Ok(rates) => rates,
+            Err(e) => {
+                self.form_error = Some(e.to_string());
+                return Ok(());
+            }
+        };
+        db.save_exchange_rates(&rates).await?;
+        self.toggle_import_form();
+        self.message = Some(format!("Imported {} rates", rates.len()));
+        self.refresh(db).await
+    }
+
+    pub async fn delete_selected(&mut self, db: &mut DB) -> Result<(), DbError> {
+        if let Some(rate) = self.rates.get(self.selected as usize) {
+            db.delete_exchange_rate(rate).await?;
+            self.refresh(db).await?;
+        }
+        Ok(())
+    }
+}
+
+pub struct ExchangeRatesEditor;
+
+impl ExchangeRatesEditor {
+    pub fn new() -> ExchangeRatesEditor {
+        ExchangeRatesEditor {}
+    }
+}
+
+impl StatefulWidget for ExchangeRatesEditor {
+    type State = ExchangeRatesState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        draw_rect_borders(
+            buf,
+            area,
+            Borders::ALL,
+            BorderType::Plain,
+            AppStyles::Main.get(),
+        );
+        let area = Rect {
+            x: area.x + 1,
+            y: area.y + 1,
+            width: area.width - 2,
+            height: area.height - 2,
+        };
+
+        buf.set_string(
+            area.x,
+            area.y,
+            format!(
+                "{:━^1$}",
+                format!("Exchange Rates into {}", CONFIG.currency.code),
+                area.width as usize
+            ),
+            AppStyles::Main.get(),
+        );
+        // keep the last two lines for the message and key help
+        let height = area.height.saturating_sub(3) as usize;
+        let start = (state.selected as usize + 1).saturating_sub(height);
+        for (i, rate) in state.rates.iter().enumerate().skip(start).take(height) {
+            let style = if i == state.selected as usize {
+                AppStyles::Main.get()
+            } else {
+                AppStyles::Accent.get()
+            };
+            let text = format!(
+                "{}  from {}  1 {} = {} {}",
+                rate.currency,
+                rate.date.format("%d/%m/%Y"),
+                rate.currency,
+                rate.rate,
+                CONFIG.currency.code
+            );
+            buf.set_stringn(
+                area.x,
+                area.y + 1 + (i - start) as u16,
+                text,
+                area.width as usize,
+                style,
+            );
+        }
+        if state.rates.is_empty() {
+            buf.set_string(
+                area.x,
+                area.y + 1,
+                "No rates yet, press i to add one or I to import a CSV file",
+                AppStyles::Accent.get(),
+            );
+        }
+
+        if let Some(message) = &state.message {
+            buf.set_stringn(
+                area.x,
+                area.y + area.height - 2,
+                message,
+                area.width as usize,
+                AppStyles::Main.get(),
+            );
+        }
+        buf.set_stringn(
+            area.x,
+            area.y + area.height - 1,
+            format!(
+                "{:^1$}",
+                "(i) add / (x) delete / (I) import csv / (esc) back", area.width as usize
+            ),
+            area.width as usize,
+            AppStyles::Accent.get(),
+        );
+
+        let error = state
+            .form_error
+            .as_ref()
+            .map(|e| Span::styled(e.clone(), AppStyles::Expense.get()));
+        if state.show_form {
+            render_form_popup(
+                centered_rect(40, 50, area),
+                buf,
+                " New Rate ",
+                &mut state.form_state,
+                error,
+            );
+        } else if state.show_import_form {
+            render_form_popup(
+                centered_rect(50, 30, area),
+                buf,
+                " Import Rates ",
+                &mut state.import_form,
+                error,
+            );
+        }
+    }
+}
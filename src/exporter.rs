@@ -0,0 +1,241 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt, fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use chrono::{NaiveDate, NaiveTime, TimeZone, Utc};
+use serde_json::json;
+
+use crate::{
+    config::CONFIG,
+    db::{MoneyTransaction, TransactionFilter, DB},
+    money::Money,
+    paths::get_flag_value,
+    util::expand_home,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    /// Choose the format from a file extension
+    pub fn from_path(path: &Path) -> Option<ExportFormat> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(io::Error),
+    UnknownFormat(String),
+    InvalidArgument(String),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Io(e) => write!(f, "Could not write file: {}", e),
+            ExportError::UnknownFormat(path) => {
+                write!(
+                    f,
+                    "Unknown export type for '{}', expected .csv or .json",
+                    path
+                )
+            }
+            ExportError::InvalidArgument(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for ExportError {}
+
+impl From<io::Error> for ExportError {
+    fn from(e: io::Error) -> Self {
+        ExportError::Io(e)
+    }
+}
+
+/// Write transactions to a file, as CSV or JSON depending on its extension
+pub fn export_file(
+    path: &str,
+    transactions: &[MoneyTransaction],
+    attachments: &HashMap<i64, Vec<String>>,
+) -> Result<(), ExportError> {
+    let path = expand_home(path.trim());
+    let format = ExportFormat::from_path(&path)
+        .ok_or_else(|| ExportError::UnknownFormat(path.display().to_string()))?;
+    let mut file = io::BufWriter::new(fs::File::create(&path)?);
+    write_transactions(&mut file, transactions, attachments, format)?;
+    file.flush()?;
+    Ok(())
+}
+
+/// Write transactions with signed amounts, expenses being negative, in the
+/// currency given alongside them
+///
+/// Attachment paths, keyed by transaction id, are only included in JSON
+pub fn write_transactions(
+    writer: &mut impl Write,
+    transactions: &[MoneyTransaction],
+    attachments: &HashMap<i64, Vec<String>>,
+    format: ExportFormat,
+) -> io::Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            writeln!(writer, "date,title,type,amount,currency,category,details")?;
+            for t in transactions {
+                writeln!(
+                    writer,
+                    "{},{},{},{},{},{},{}",
+                    t.date.format("%Y-%m-%d"),
+                    escape_csv(&t.title),
+                    t.kind.get_name(),
+                    t.signed_amount().to_decimal_string(),
+                    currency_code(t),
+                    escape_csv(t.category.as_deref().unwrap_or("")),
+                    escape_csv(&t.details)
+                )?;
+            }
+        }
+        ExportFormat::Json => {
+            let rows: Vec<serde_json::Value> = transactions
+                .iter()
+                .map(|t| {
+                    json!({
+                        "date": t.date.format("%Y-%m-%d").to_string(),
+                        "title": t.title,
+                        "type": t.kind.get_name(),
+                        "amount": t.signed_amount().to_decimal_string(),
+                        "currency": currency_code(t),
+                        "category": t.category,
+                        "details": t.details,
+                        "attachments": attachments.get(&t.id).cloned().unwrap_or_default(),
+                    })
+                })
+                .collect();
+            serde_json::to_writer_pretty(&mut *writer, &rows)?;
+            writeln!(writer)?;
+        }
+    }
+    Ok(())
+}
+
+fn currency_code(transaction: &MoneyTransaction) -> &str {
+    transaction
+        .currency
+        .as_deref()
+        .unwrap_or(&CONFIG.currency.code)
+}
+
+/// Quote a CSV field if it contains anything that would split it
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Parse a `YYYY-MM-DD` date from an export date range, empty for no limit
+pub fn parse_range_date(text: &str) -> Result<Option<NaiveDate>, ExportError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .map(Some)
+        .map_err(|_| ExportError::InvalidArgument(format!("Invalid date '{}'", text)))
+}
+
+/// Limit a filter to transactions from the start of `from` to the end of `to`
+pub fn set_date_range(
+    filter: &mut TransactionFilter,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) {
+    filter.start_date = from.map(|d| Utc.from_utc_datetime(&d.and_time(NaiveTime::MIN)));
+    filter.end_date = to.map(|d| Utc.from_utc_datetime(&d.and_hms_opt(23, 59, 59).unwrap()));
+}
+
+/// Run `util-tui export <file> [--from YYYY-MM-DD] [--to YYYY-MM-DD]
+/// [--keyword TEXT] [--details TEXT] [--min AMOUNT] [--max AMOUNT]`
+pub async fn run_cli(db: &mut DB, path: &str) -> Result<usize, Box<dyn Error>> {
+    let mut filter = TransactionFilter::new();
+    // the money screen caps amounts by default, exports include everything
+    // unless `--max` is given
+    filter.max = Money::from_minor(i64::MAX);
+    if let Some(keyword) = get_flag_value("--keyword") {
+        filter.keyword = keyword;
+    }
+    if let Some(details) = get_flag_value("--details") {
+        filter.details = details;
+    }
+    for (flag, bound) in [("--min", &mut filter.min), ("--max", &mut filter.max)] {
+        if let Some(value) = get_flag_value(flag) {
+            *bound = Money::parse(&value).ok_or_else(|| {
+                ExportError::InvalidArgument(format!("Invalid amount for {}: '{}'", flag, value))
+            })?;
+        }
+    }
+    let from = parse_range_date(&get_flag_value("--from").unwrap_or_default())?;
+    let to = parse_range_date(&get_flag_value("--to").unwrap_or_default())?;
+    set_date_range(&mut filter, from, to);
+
+    let transactions = db.get_filtered_transactions(&filter).await?;
+    export_file(path, &transactions, &db.get_attachment_paths().await?)?;
+    Ok(transactions.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::db::TransactionKind;
+
+    #[test]
+    fn test_write_transactions() {
+        let mut transaction = MoneyTransaction::new(
+            "Shop, \"Ltd\"".to_owned(),
+            Money::from_minor(1250),
+            "".to_owned(),
+            Utc.with_ymd_and_hms(2023, 7, 14, 12, 0, 0).unwrap(),
+            TransactionKind::Expense,
+            None,
+        );
+        transaction.category = Some("Groceries".to_owned());
+
+        let mut csv = vec![];
+        let attachments = HashMap::from([(transaction.id, vec!["/receipts/shop.pdf".to_owned()])]);
+        write_transactions(
+            &mut csv,
+            &[transaction.clone()],
+            &attachments,
+            ExportFormat::Csv,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "date,title,type,amount,currency,category,details\n\
+            2023-07-14,\"Shop, \"\"Ltd\"\"\",Expense,-12.50,GBP,Groceries,\n"
+        );
+
+        let mut json = vec![];
+        write_transactions(&mut json, &[transaction], &attachments, ExportFormat::Json).unwrap();
+        let rows: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(rows[0]["title"], "Shop, \"Ltd\"");
+        assert_eq!(rows[0]["amount"], "-12.50");
+        assert_eq!(rows[0]["category"], "Groceries");
+        assert_eq!(rows[0]["attachments"][0], "/receipts/shop.pdf");
+    }
+}
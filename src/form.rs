@@ -8,12 +8,14 @@ use tui::{
     layout::Rect,
     style::Style,
     symbols,
-    widgets::{BorderType, Borders, StatefulWidget},
+    text::Span,
+    widgets::{BorderType, Borders, Paragraph, StatefulWidget, Widget, Wrap},
 };
 
 use crate::{
+    money::Money,
     styles::AppStyles,
-    util::{draw_rect_borders, generic_increment},
+    util::{clear_area, draw_rect_borders, generic_increment},
 };
 
 // ----------------------------------------------------------------------------
@@ -45,6 +47,7 @@ pub enum FormValue {
     Integer(u32),
     Float(f32),
     Date(DateTime<Utc>),
+    Money(Money),
 }
 
 impl FormValue {
@@ -103,6 +106,13 @@ impl FormValue {
         }
         None
     }
+
+    pub fn try_get_money_value(&self) -> Option<&Money> {
+        if let FormValue::Money(m) = self {
+            return Some(m);
+        }
+        None
+    }
 }
 
 pub trait FormField {
@@ -110,6 +120,7 @@ pub trait FormField {
     fn get_internal_value(&self) -> &FormValue;
     fn get_default_value(&self) -> &FormValue;
     fn reset_value(&mut self);
+    fn set_value(&mut self, value: FormValue);
     fn is_required(&self) -> bool;
     fn receive_input(&mut self, key: &KeyCode);
     fn get_style(&self) -> &FormFieldStyle;
@@ -170,6 +181,12 @@ impl FormField for TextField {
         self.value = self.default_value.clone();
     }
 
+    fn set_value(&mut self, value: FormValue) {
+        if let FormValue::Text(_) = value {
+            self.value = value;
+        }
+    }
+
     fn receive_input(&mut self, key: &KeyCode) {
         let current_value = self.value.try_get_text_value_mut().unwrap();
         match key {
@@ -225,6 +242,12 @@ impl FormField for IntegerField {
         self.value = self.default_value.clone();
     }
 
+    fn set_value(&mut self, value: FormValue) {
+        if let FormValue::Integer(i) = value {
+            self.value = FormValue::Integer(i.clamp(self.min, self.max));
+        }
+    }
+
     fn receive_input(&mut self, key: &KeyCode) {
         let current_value = self.value.try_get_integer_value_mut().unwrap();
         match key {
@@ -283,6 +306,14 @@ impl FormField for FloatField {
         self.display_value = "".to_owned();
     }
 
+    fn set_value(&mut self, value: FormValue) {
+        if let FormValue::Float(f) = value {
+            let f = f.clamp(self.min, self.max);
+            self.value = FormValue::Float(f);
+            self.display_value = f.to_string();
+        }
+    }
+
     fn receive_input(&mut self, key: &KeyCode) {
         let current_value = self.value.try_get_float_value_mut().unwrap();
         match key {
@@ -313,6 +344,76 @@ impl FormField for FloatField {
     }
 }
 
+/// Field for an amount of money, typed as a decimal with at most the configured
+/// number of decimal places
+pub struct MoneyField {
+    value: FormValue,
+    default_value: FormValue,
+    display_value: String,
+    max: Money,
+    is_required: bool,
+    style: FormFieldStyle,
+}
+
+impl MoneyField {
+    pub fn new(
+        default_value: Money,
+        max: Money,
+        is_required: bool,
+        style: FormFieldStyle,
+    ) -> MoneyField {
+        MoneyField {
+            value: FormValue::Money(default_value),
+            default_value: FormValue::Money(default_value),
+            display_value: "".to_owned(),
+            max,
+            is_required,
+            style,
+        }
+    }
+}
+
+impl FormField for MoneyField {
+    form_field_access_funcs!();
+
+    fn get_display_value(&self) -> String {
+        self.display_value.clone()
+    }
+
+    fn reset_value(&mut self) {
+        self.value = self.default_value.clone();
+        self.display_value = "".to_owned();
+    }
+
+    fn set_value(&mut self, value: FormValue) {
+        if let FormValue::Money(m) = value {
+            let m = m.clamp(Money::default(), self.max);
+            self.value = FormValue::Money(m);
+            self.display_value = m.to_decimal_string();
+        }
+    }
+
+    fn receive_input(&mut self, key: &KeyCode) {
+        match key {
+            KeyCode::Char(c @ ('0'..='9' | '.')) => {
+                let new_value = format!("{}{}", self.display_value, c);
+                if let Some(m) = Money::parse(&new_value) {
+                    if m <= self.max {
+                        self.display_value = new_value;
+                        self.value = FormValue::Money(m);
+                    }
+                }
+            }
+            KeyCode::Backspace => {
+                self.display_value.pop();
+                self.value =
+                    FormValue::Money(Money::parse(&self.display_value).unwrap_or_default());
+            }
+            _ => {}
+        }
+    }
+}
+
 pub struct DateField {
     value: FormValue,
     default_value: FormValue,
@@ -356,6 +457,15 @@ impl FormField for DateField {
         self.year = date.year().to_string()[2..4].to_string();
     }
 
+    fn set_value(&mut self, value: FormValue) {
+        if let FormValue::Date(date) = value {
+            self.day = format!("{:0>2}", date.day());
+            self.month = format!("{:0>2}", date.month());
+            self.year = date.year().to_string()[2..4].to_string();
+            self.value = value;
+        }
+    }
+
     fn receive_input(&mut self, key: &KeyCode) {
         let current_value = self.value.try_get_date_value_mut().unwrap();
         let num_of_days: HashMap<u32, u32> = HashMap::from([
@@ -424,6 +534,81 @@ impl FormField for DateField {
         }
     }
 }
+
+/// Text field that cycles through a list of options with Left/Right
+///
+/// When `allow_custom` is set a value not in the options can also be typed in
+pub struct ChoiceField {
+    value: FormValue,
+    default_value: FormValue,
+    options: Vec<String>,
+    allow_custom: bool,
+    is_required: bool,
+    style: FormFieldStyle,
+}
+
+impl ChoiceField {
+    pub fn new(
+        options: Vec<String>,
+        default_value: String,
+        allow_custom: bool,
+        is_required: bool,
+        style: FormFieldStyle,
+    ) -> ChoiceField {
+        ChoiceField {
+            value: FormValue::Text(default_value.clone()),
+            default_value: FormValue::Text(default_value),
+            options,
+            allow_custom,
+            is_required,
+            style,
+        }
+    }
+}
+
+impl FormField for ChoiceField {
+    form_field_access_funcs!();
+
+    fn get_display_value(&self) -> String {
+        self.value.try_get_text_value().unwrap().clone()
+    }
+
+    fn reset_value(&mut self) {
+        self.value = self.default_value.clone();
+    }
+
+    fn set_value(&mut self, value: FormValue) {
+        if let FormValue::Text(s) = &value {
+            if self.allow_custom || self.options.contains(s) {
+                self.value = value;
+            }
+        }
+    }
+
+    fn receive_input(&mut self, key: &KeyCode) {
+        let current_value = self.value.try_get_text_value_mut().unwrap();
+        match key {
+            KeyCode::Left | KeyCode::Right if !self.options.is_empty() => {
+                let len = self.options.len();
+                let current = self.options.iter().position(|o| o == current_value);
+                let next = match (current, key) {
+                    (Some(i), KeyCode::Right) => (i + 1) % len,
+                    (Some(i), _) => (i + len - 1) % len,
+                    (None, KeyCode::Right) => 0,
+                    (None, _) => len - 1,
+                };
+                *current_value = self.options[next].clone();
+            }
+            KeyCode::Backspace if self.allow_custom => {
+                current_value.pop();
+            }
+            KeyCode::Char(char) if self.allow_custom => {
+                current_value.push(*char);
+            }
+            _ => {}
+        }
+    }
+}
 // ----------------------------------------------------------------------------
 
 pub struct FormState {
@@ -583,3 +768,45 @@ impl StatefulWidget for Form {
 }
 
 // ----------------------------------------------------------------------------
+
+/// Draw a form in a bordered popup, with an optional message under the fields
+pub fn render_form_popup(
+    area: Rect,
+    buf: &mut Buffer,
+    title_text: &str,
+    form_state: &mut FormState,
+    message: Option<Span>,
+) {
+    clear_area(buf, area);
+    draw_rect_borders(
+        buf,
+        area,
+        Borders::ALL,
+        BorderType::Thick,
+        AppStyles::Main.get(),
+    );
+    buf.set_string(
+        area.x + ((area.width - 2) / 2) - (title_text.len() as u16 / 2),
+        area.y,
+        title_text,
+        AppStyles::Main.get(),
+    );
+    let area = Rect {
+        x: area.x + 1,
+        y: area.y + 1,
+        width: area.width - 2,
+        height: area.height - 2,
+    };
+    Form.render(area, buf, form_state);
+    if let Some(message) = message {
+        let fields_height = (form_state.get_fields().len() as u16 * 3).min(area.height);
+        Paragraph::new(message).wrap(Wrap { trim: false }).render(
+            Rect {
+                y: area.y + fields_height,
+                height: area.height - fields_height,
+                ..area
+            },
+            buf,
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+
+use crate::db::MoneyTransaction;
+
+/// A transaction matching the query, with the positions of the matched
+/// characters in its title and details
+#[derive(Debug)]
+pub struct FuzzyMatch {
+    /// Position of the transaction in the searched list
+    pub index: usize,
+    pub score: i64,
+    pub title_indices: Vec<usize>,
+    pub details_indices: Vec<usize>,
+}
+
+/// Transactions ranked by how well their title or details fuzzy match a query,
+/// re-ranked as the query is typed
+#[derive(Debug)]
+pub struct FuzzySearch {
+    query: String,
+    transactions: Vec<MoneyTransaction>,
+    matches: Vec<FuzzyMatch>,
+}
+
+impl FuzzySearch {
+    pub fn new(transactions: Vec<MoneyTransaction>) -> FuzzySearch {
+        let mut search = FuzzySearch {
+            query: String::new(),
+            transactions,
+            matches: Vec::new(),
+        };
+        search.rank();
+        search
+    }
+
+    pub fn get_query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.rank();
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.rank();
+    }
+
+    /// Best match first, an empty query matches everything in its original
+    /// order
+    pub fn get_matches(&self) -> &[FuzzyMatch] {
+        &self.matches
+    }
+
+    pub fn get_transaction(&self, m: &FuzzyMatch) -> &MoneyTransaction {
+        &self.transactions[m.index]
+    }
+
+    fn rank(&mut self) {
+        let matcher = SkimMatcherV2::default();
+        let query = self.query.trim();
+        self.matches = self
+            .transactions
+            .iter()
+            .enumerate()
+            .filter_map(|(index, t)| {
+                if query.is_empty() {
+                    return Some(FuzzyMatch {
+                        index,
+                        score: 0,
+                        title_indices: vec![],
+                        details_indices: vec![],
+                    });
+                }
+                let title = matcher.fuzzy_indices(&t.title, query);
+                let details = matcher.fuzzy_indices(&t.details, query);
+                let score = title
+                    .iter()
+                    .chain(details.iter())
+                    .map(|(score, _)| *score)
+                    .max()?;
+                Some(FuzzyMatch {
+                    index,
+                    score,
+                    title_indices: title.map(|(_, i)| i).unwrap_or_default(),
+                    details_indices: details.map(|(_, i)| i).unwrap_or_default(),
+                })
+            })
+            .collect();
+        // stable, so equal scores keep the list's order
+        self.matches.sort_by_key(|m| std::cmp::Reverse(m.score));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::{db::TransactionKind, money::Money};
+
+    #[test]
+    fn test_fuzzy_search() {
+        let transactions = [
+            ("Tesco Express", "meal deal"),
+            ("Train ticket", "london return"),
+            ("Netflix", ""),
+        ]
+        .iter()
+        .map(|(title, details)| {
+            MoneyTransaction::new(
+                title.to_string(),
+                Money::from_minor(100),
+                details.to_string(),
+                Utc::now(),
+                TransactionKind::Expense,
+                None,
+            )
+        })
+        .collect();
+        let mut search = FuzzySearch::new(transactions);
+        assert_eq!(search.get_matches().len(), 3);
+
+        for c in "tex".chars() {
+            search.push(c);
+        }
+        let matches = search.get_matches();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(search.get_transaction(&matches[0]).title, "Tesco Express");
+        assert_eq!(matches[0].title_indices, vec![0, 6, 7]);
+
+        // details are searched too
+        search.pop();
+        search.pop();
+        search.pop();
+        for c in "ldn".chars() {
+            search.push(c);
+        }
+        let matches = search.get_matches();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(search.get_transaction(&matches[0]).title, "Train ticket");
+        assert_eq!(matches[0].details_indices, vec![0, 3, 5]);
+    }
+}
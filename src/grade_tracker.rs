@@ -1,59 +1,292 @@
-use std::{
-    fs::{self, File},
-    io::{self, BufWriter, Write},
-};
+use std::{fs, path::Path};
 
-use log::info;
-use serde::{Deserialize, Serialize};
+use chrono::NaiveDate;
+use log::{error, info};
+use serde::Deserialize;
 use tui::{
     buffer::Buffer,
-    layout::Rect,
-    widgets::{BorderType, Borders, StatefulWidget},
+    layout::{Alignment, Rect},
+    text::{Span, Spans},
+    widgets::{BorderType, Borders, Paragraph, StatefulWidget, Widget, Wrap},
 };
 
 use crate::{
+    config::CONFIG,
+    db::{DbError, Grade, Module, DB},
     form::{
-        FloatField, Form, FormField, FormFieldStyle, FormState, FormValue, IntegerField, TextField,
+        render_form_popup, FloatField, FormField, FormFieldStyle, FormState, FormValue,
+        IntegerField, TextField,
     },
+    paths::PATHS,
+    popup::Popup,
     styles::AppStyles,
-    util::{centered_rect, clear_area, draw_rect_borders, generic_increment, getcwd},
+    util::{centered_rect, draw_rect_borders, generic_increment},
 };
 
-#[derive(Deserialize, Serialize, Debug)]
-pub struct Grade {
-    pub name: String,
-    pub percentage: f32,
-    pub weight: f32,
+/// Degree classification for an average mark
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Classification {
+    First,
+    UpperSecond,
+    LowerSecond,
+    Third,
+    Fail,
+}
+
+impl Classification {
+    pub fn from_mark(mark: f32) -> Classification {
+        [
+            Classification::First,
+            Classification::UpperSecond,
+            Classification::LowerSecond,
+            Classification::Third,
+        ]
+        .into_iter()
+        .find(|c| mark >= c.get_boundary())
+        .unwrap_or(Classification::Fail)
+    }
+
+    /// Read a classification by its name, e.g. `2:1` or `first`
+    pub fn parse(text: &str) -> Option<Classification> {
+        [
+            Classification::First,
+            Classification::UpperSecond,
+            Classification::LowerSecond,
+            Classification::Third,
+        ]
+        .into_iter()
+        .find(|c| c.get_name().eq_ignore_ascii_case(text.trim()))
+    }
+
+    /// Lowest mark that gets the classification
+    pub fn get_boundary(&self) -> f32 {
+        match self {
+            Classification::First => 70.,
+            Classification::UpperSecond => 60.,
+            Classification::LowerSecond => 50.,
+            Classification::Third => 40.,
+            Classification::Fail => 0.,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        match self {
+            Classification::First => "First",
+            Classification::UpperSecond => "2:1",
+            Classification::LowerSecond => "2:2",
+            Classification::Third => "Third",
+            Classification::Fail => "Fail",
+        }
+    }
+}
+
+/// What's needed on a module's unmarked assessments to reach a target mark
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RequiredAverage {
+    /// The marks so far reach the target whatever happens next
+    Reached,
+    /// Average percentage needed on the unmarked assessments, which add up to
+    /// `remaining_weight`
+    Needed { average: f32, remaining_weight: f32 },
+    /// Even full marks on everything left falls short, `best` being the
+    /// highest module mark still possible
+    Impossible { best: f32 },
+}
+
+/// Average needed on the unmarked assessments to finish on `target`, the
+/// module mark being the average of all assessments weighted by their weights
+/// as in `Module::mark`, whatever the weights add up to
+pub fn required_average(module: &Module, target: f32) -> RequiredAverage {
+    let earned: f32 = module.marked_grades().map(|(g, p)| p * g.weight).sum();
+    let marked_weight: f32 = module.marked_grades().map(|(g, _)| g.weight).sum();
+    let remaining_weight: f32 = module
+        .grades
+        .iter()
+        .filter(|g| g.percentage.is_none())
+        .map(|g| g.weight)
+        .sum();
+    let total_weight = marked_weight + remaining_weight;
+    if total_weight <= 0. {
+        return RequiredAverage::Needed {
+            average: target,
+            remaining_weight,
+        };
+    }
+    let best = (earned + 100. * remaining_weight) / total_weight;
+    if earned / total_weight >= target {
+        RequiredAverage::Reached
+    } else if best < target {
+        RequiredAverage::Impossible { best }
+    } else {
+        RequiredAverage::Needed {
+            average: (target * total_weight - earned) / remaining_weight,
+            remaining_weight,
+        }
+    }
+}
+
+/// Average mark of a year's modules weighted by their credits, modules with
+/// nothing marked yet are left out
+pub fn year_average<'a>(modules: impl IntoIterator<Item = &'a Module>) -> Option<f32> {
+    let (total, credits) = modules
+        .into_iter()
+        .filter_map(|m| Some((m.mark()?, m.credits as f32)))
+        .fold((0., 0.), |(total, credits), (mark, c)| {
+            (total + mark * c, credits + c)
+        });
+    (credits > 0.).then(|| total / credits)
+}
+
+/// Average over all years, each year's average counting for its weight in
+/// `year_weights` where the first weight is for year 1, years with no weight
+/// or no marks are left out
+pub fn degree_average(modules: &[Module], year_weights: &[f32]) -> Option<f32> {
+    let (total, weights) = year_weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0.)
+        .filter_map(|(i, weight)| {
+            let year = i as u32 + 1;
+            let average = year_average(modules.iter().filter(|m| m.year == year))?;
+            Some((average, *weight))
+        })
+        .fold((0., 0.), |(total, weights), (average, weight)| {
+            (total + average * weight, weights + weight)
+        });
+    (weights > 0.).then(|| total / weights)
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-pub struct Module {
+/// An assessment still to be marked that has a due date
+#[derive(Debug, Clone)]
+pub struct Deadline {
+    pub date: NaiveDate,
+    pub module: String,
     pub name: String,
-    pub grades: Vec<Grade>,
+}
+
+/// A module as it was stored in the old grades JSON files
+#[derive(Deserialize)]
+struct LegacyModule {
+    name: String,
+    grades: Vec<LegacyGrade>,
+}
+
+#[derive(Deserialize)]
+struct LegacyGrade {
+    name: String,
+    percentage: f32,
+    weight: f32,
+}
+
+impl LegacyModule {
+    fn into_module(self, year: u32) -> Module {
+        let mut module = Module::new(self.name, year);
+        module.grades = self
+            .grades
+            .into_iter()
+            // unmarked work was kept as 0
+            .map(|g| Grade::new(g.name, Some(g.percentage).filter(|p| *p > 0.), g.weight))
+            .collect();
+        module
+    }
+}
+
+/// Read the modules from one of the old grades JSON files
+fn read_legacy_file(path: &Path, year: u32) -> Result<Vec<Module>, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let modules: Vec<LegacyModule> = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    Ok(modules.into_iter().map(|m| m.into_module(year)).collect())
+}
+
+/// Year in an old grades file's name, e.g. 2 for `grades_y2.json`
+fn legacy_file_year(path: &Path) -> Option<u32> {
+    path.file_stem()?
+        .to_str()?
+        .strip_prefix("grades_y")?
+        .parse()
+        .ok()
+}
+
+/// Move grades from the old JSON files into the database the first time the
+/// app runs without any modules, each file imported is renamed with an
+/// `.imported` suffix so it isn't read again
+///
+/// `grades_yN.json` holds year N and `grades.json` the year after those
+pub async fn import_legacy_files(db: &mut DB) -> Result<(), DbError> {
+    if !db.get_modules().await?.is_empty() {
+        return Ok(());
+    }
+    let paths: Vec<_> = PATHS
+        .get_legacy_grades_paths()
+        .into_iter()
+        .filter(|p| p.is_file())
+        .collect();
+    let current_year = paths
+        .iter()
+        .filter_map(|p| legacy_file_year(p))
+        .max()
+        .unwrap_or(0)
+        + 1;
+    for path in paths {
+        let year = legacy_file_year(&path).unwrap_or(current_year);
+        let modules = match read_legacy_file(&path, year) {
+            Ok(modules) => modules,
+            Err(e) => {
+                error!("could not import grades from {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        db.import_modules(&modules).await?;
+        info!("imported {} modules from {}", modules.len(), path.display());
+        let mut imported = path.clone().into_os_string();
+        imported.push(".imported");
+        if let Err(e) = fs::rename(&path, &imported) {
+            error!("could not rename {}: {}", path.display(), e);
+        }
+    }
+    Ok(())
 }
 
 #[derive(Debug)]
 pub struct GradeTrackerState {
+    /// Modules from every year
     pub data: Vec<Module>,
+    /// Year of study being shown
+    pub year: u32,
+    /// Index into the shown year's modules
     pub selected: u32,
+    /// Index into the selected module's assessments, `None` when the module
+    /// itself is selected
+    pub selected_grade: Option<usize>,
     pub show_form: bool,
     pub form_state: FormState,
+    /// Assessment being changed by the form, `None` when adding one
+    editing_grade: Option<i64>,
+    /// Shown in the assessment form when it can't be submitted
+    form_error: Option<String>,
+    pub show_module_form: bool,
+    pub module_form: FormState,
+    /// Module being changed by the module form, `None` when adding one
+    editing_module: Option<i64>,
+    pub show_delete_popup: bool,
+    pub show_target_form: bool,
+    pub target_form: FormState,
+    /// Result of the target calculator, shown under its form
+    target_message: Option<Span<'static>>,
 }
 
 impl GradeTrackerState {
-    pub fn new() -> GradeTrackerState {
+    pub async fn new(db: &mut DB) -> Result<GradeTrackerState, DbError> {
         let mut form_state = FormState::new();
         form_state.add_field(Box::new(TextField::new(
             "".to_owned(),
             true,
             FormFieldStyle::new("Title".to_owned()),
         )));
-        form_state.add_field(Box::new(FloatField::new(
-            0.,
-            0.,
-            100.,
-            true,
-            FormFieldStyle::new("Percentage".to_owned()),
+        form_state.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            false,
+            FormFieldStyle::new("Mark, empty if not marked yet".to_owned()),
         )));
         form_state.add_field(Box::new(FloatField::new(
             0.,
@@ -62,64 +295,391 @@ impl GradeTrackerState {
             true,
             FormFieldStyle::new("Weight".to_owned()),
         )));
-        GradeTrackerState {
-            data: GradeTrackerState::get_data(),
+        form_state.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            false,
+            FormFieldStyle::new("Due date, YYYY-MM-DD".to_owned()),
+        )));
+        let mut module_form = FormState::new();
+        module_form.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            true,
+            FormFieldStyle::new("Name".to_owned()),
+        )));
+        module_form.add_field(Box::new(IntegerField::new(
+            1,
+            1,
+            10,
+            true,
+            FormFieldStyle::new("Year".to_owned()),
+        )));
+        module_form.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            false,
+            FormFieldStyle::new("Term".to_owned()),
+        )));
+        module_form.add_field(Box::new(IntegerField::new(
+            15,
+            0,
+            240,
+            true,
+            FormFieldStyle::new("Credits".to_owned()),
+        )));
+        let mut target_form = FormState::new();
+        target_form.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            true,
+            FormFieldStyle::new("Target mark or classification, e.g. 65 or 2:1".to_owned()),
+        )));
+        let data = db.get_modules().await?;
+        // start on the latest year
+        let year = data.iter().map(|m| m.year).max().unwrap_or(1);
+        Ok(GradeTrackerState {
+            data,
+            year,
             selected: 0,
+            selected_grade: None,
             show_form: false,
             form_state,
+            editing_grade: None,
+            form_error: None,
+            show_module_form: false,
+            module_form,
+            editing_module: None,
+            show_delete_popup: false,
+            show_target_form: false,
+            target_form,
+            target_message: None,
+        })
+    }
+
+    /// Reload the modules, keeping the selection within the shown year
+    async fn refresh(&mut self, db: &mut DB) -> Result<(), DbError> {
+        self.data = db.get_modules().await?;
+        let count = self.get_year_modules().len() as u32;
+        self.selected = self.selected.min(count.saturating_sub(1));
+        let grades = self.get_selected_module().map_or(0, |m| m.grades.len());
+        self.selected_grade = self
+            .selected_grade
+            .filter(|_| grades > 0)
+            .map(|i| i.min(grades - 1));
+        Ok(())
+    }
+
+    /// Years with modules, along with the one being shown
+    pub fn get_years(&self) -> Vec<u32> {
+        let mut years: Vec<u32> = self.data.iter().map(|m| m.year).collect();
+        years.push(self.year);
+        years.sort_unstable();
+        years.dedup();
+        years
+    }
+
+    pub fn get_year_modules(&self) -> Vec<&Module> {
+        self.data.iter().filter(|m| m.year == self.year).collect()
+    }
+
+    pub fn get_selected_module(&self) -> Option<&Module> {
+        self.get_year_modules().get(self.selected as usize).copied()
+    }
+
+    /// Due dates of unmarked assessments from every year, soonest first
+    pub fn get_deadlines(&self) -> Vec<Deadline> {
+        let mut deadlines: Vec<Deadline> = self
+            .data
+            .iter()
+            .flat_map(|m| {
+                m.grades
+                    .iter()
+                    .filter(|g| g.percentage.is_none())
+                    .filter_map(|g| {
+                        Some(Deadline {
+                            date: g.due_date?,
+                            module: m.name.clone(),
+                            name: g.name.clone(),
+                        })
+                    })
+            })
+            .collect();
+        deadlines.sort_by_key(|d| d.date);
+        deadlines
+    }
+
+    pub fn get_selected_grade(&self) -> Option<&Grade> {
+        self.get_selected_module()?.grades.get(self.selected_grade?)
+    }
+
+    pub fn increment_year(&mut self, amount: i32) {
+        let years = self.get_years();
+        let current = years.iter().position(|y| *y == self.year).unwrap_or(0) as u32;
+        let mut index = current;
+        generic_increment(&mut index, 0, years.len() as u32 - 1, amount);
+        if index != current {
+            self.year = years[index as usize];
+            self.selected = 0;
+            self.selected_grade = None;
         }
     }
 
-    pub fn submit_form(&mut self) {
-        let fields = self.form_state.get_fields();
-        let vals: Vec<&FormValue> = fields.iter().map(|f| f.get_internal_value()).collect();
-        match vals.as_slice() {
-            [title, percentage, weight] => {
-                let grade = Grade {
-                    name: title.try_get_text_value().unwrap().clone(),
-                    percentage: *percentage.try_get_float_value().unwrap(),
-                    weight: *weight.try_get_float_value().unwrap(),
-                };
-                if self.write_data().is_ok() {
-                    self.add_grade_to_selected(grade);
-                    self.form_state.reset_fields();
-                }
+    /// The form currently taking input
+    pub fn get_active_form_mut(&mut self) -> &mut FormState {
+        if self.show_module_form {
+            &mut self.module_form
+        } else if self.show_target_form {
+            &mut self.target_form
+        } else {
+            &mut self.form_state
+        }
+    }
+
+    /// Open an empty module form to add a module to the shown year
+    pub fn open_add_module_form(&mut self) {
+        self.module_form.reset_fields();
+        self.module_form.get_fields_mut()[1].set_value(FormValue::Integer(self.year));
+        self.editing_module = None;
+        self.show_module_form = true;
+    }
+
+    /// Open the selected module's name, year, term and credits for editing
+    pub fn toggle_module_form(&mut self) {
+        if self.show_module_form {
+            self.show_module_form = false;
+            self.editing_module = None;
+            self.module_form.reset_fields();
+            return;
+        }
+        if let Some(module) = self.get_selected_module() {
+            let values = [
+                FormValue::Text(module.name.clone()),
+                FormValue::Integer(module.year),
+                FormValue::Text(module.term.clone().unwrap_or_default()),
+                FormValue::Integer(module.credits),
+            ];
+            self.editing_module = Some(module.id);
+            for (field, value) in self.module_form.get_fields_mut().iter_mut().zip(values) {
+                field.set_value(value);
             }
-            [..] => {}
+            self.show_module_form = true;
         }
     }
 
-    fn get_data() -> Vec<Module> {
-        let str_data = fs::read_to_string(format!("{}/src/grades.json", getcwd()))
-            .unwrap_or(String::from("[]"));
-        serde_json::from_str(&str_data).unwrap_or(vec![])
+    /// Add or save the module in the module form, following it to its year
+    pub async fn submit_module_form(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let fields = self.module_form.get_fields();
+        let vals: Vec<&FormValue> = fields.iter().map(|f| f.get_internal_value()).collect();
+        if let [name, year, term, credits] = vals.as_slice() {
+            let name = name.try_get_text_value().unwrap().trim().to_owned();
+            if name.is_empty() {
+                return Ok(());
+            }
+            let term = term.try_get_text_value().unwrap().trim().to_owned();
+            let mut module = Module::new(name, *year.try_get_integer_value().unwrap());
+            module.term = (!term.is_empty()).then_some(term);
+            module.credits = *credits.try_get_integer_value().unwrap();
+            match self.editing_module {
+                Some(id) => {
+                    module.id = id;
+                    db.update_module(&module).await?;
+                }
+                None => module.id = db.add_module(&module).await?,
+            }
+            self.year = module.year;
+            self.refresh(db).await?;
+            self.selected = self
+                .get_year_modules()
+                .iter()
+                .position(|m| m.id == module.id)
+                .unwrap_or(0) as u32;
+            self.selected_grade = None;
+        }
+        self.toggle_module_form();
+        Ok(())
     }
 
-    fn add_grade_to_selected(&mut self, grade: Grade) {
-        self.data[self.selected as usize].grades.push(grade);
+    /// Open the assessment form, filled with the selected assessment to edit it
+    /// when `edit` is set
+    pub fn open_form(&mut self, edit: bool) {
+        if self.get_selected_module().is_none() {
+            return;
+        }
+        self.form_state.reset_fields();
+        self.editing_grade = None;
+        if edit {
+            let grade = match self.get_selected_grade() {
+                Some(grade) => grade,
+                None => return,
+            };
+            let values = [
+                FormValue::Text(grade.name.clone()),
+                FormValue::Text(grade.percentage.map_or(String::new(), |p| p.to_string())),
+                FormValue::Float(grade.weight),
+                FormValue::Text(
+                    grade
+                        .due_date
+                        .map_or(String::new(), |d| d.format("%Y-%m-%d").to_string()),
+                ),
+            ];
+            self.editing_grade = Some(grade.id);
+            for (field, value) in self.form_state.get_fields_mut().iter_mut().zip(values) {
+                field.set_value(value);
+            }
+        }
+        self.show_form = true;
     }
 
-    fn write_data(&self) -> io::Result<()> {
-        let file = match File::options()
-            .write(true)
-            .truncate(true)
-            .open(format!("{}/src/grades.json", getcwd()))
-        {
-            Ok(file) => file,
-            Err(_) => panic!("no file grades.json"),
-        };
-        let mut writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(&mut writer, &self.data)?;
-        writer.flush()?;
+    /// Add the assessment in the form to the selected module, or save the one
+    /// being edited
+    pub async fn submit_form(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let fields = self.form_state.get_fields();
+        let vals: Vec<&FormValue> = fields.iter().map(|f| f.get_internal_value()).collect();
+        if let [title, percentage, weight, due_date] = vals.as_slice() {
+            let title = title.try_get_text_value().unwrap().trim().to_owned();
+            if title.is_empty() {
+                return Ok(());
+            }
+            let percentage = match percentage.try_get_text_value().unwrap().trim() {
+                "" => None,
+                text => match text.trim_end_matches('%').parse::<f32>() {
+                    Ok(p) if (0. ..=100.).contains(&p) => Some(p),
+                    _ => {
+                        self.form_error = Some("Enter a mark from 0 to 100".to_owned());
+                        return Ok(());
+                    }
+                },
+            };
+            let due_date = match due_date.try_get_text_value().unwrap().trim() {
+                "" => None,
+                text => match NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+                    Ok(date) => Some(date),
+                    Err(_) => {
+                        self.form_error = Some(format!("Invalid date '{}'", text));
+                        return Ok(());
+                    }
+                },
+            };
+            let mut grade = Grade::new(title, percentage, *weight.try_get_float_value().unwrap());
+            grade.due_date = due_date;
+            match (self.editing_grade, self.get_selected_module()) {
+                (Some(id), _) => {
+                    grade.id = id;
+                    db.update_grade(&grade).await?;
+                }
+                (None, Some(module)) => {
+                    grade.module_id = module.id;
+                    db.add_grade(&grade).await?;
+                }
+                (None, None) => {}
+            }
+            self.refresh(db).await?;
+        }
+        self.toggle_form();
         Ok(())
     }
 
     pub fn increment_selected(&mut self, amount: i32) {
-        generic_increment(&mut self.selected, 0, self.data.len() as u32 - 1, amount);
+        let count = self.get_year_modules().len() as u32;
+        if count == 0 {
+            return;
+        }
+        generic_increment(&mut self.selected, 0, count - 1, amount);
+        self.selected_grade = None;
+    }
+
+    /// Move between the selected module's assessments, going before the first
+    /// one selects the module again
+    pub fn increment_selected_grade(&mut self, amount: i32) {
+        let count = self.get_selected_module().map_or(0, |m| m.grades.len()) as i32;
+        let index = self.selected_grade.map_or(-1, |i| i as i32) + amount;
+        self.selected_grade = match index {
+            i if i < 0 => None,
+            i => Some(i.min(count - 1) as usize).filter(|_| count > 0),
+        };
     }
 
     pub fn toggle_form(&mut self) {
         self.show_form = !self.show_form;
+        self.form_error = None;
+        if !self.show_form {
+            self.editing_grade = None;
+            self.form_state.reset_fields();
+        }
+    }
+
+    pub fn toggle_target_form(&mut self) {
+        self.show_target_form = !self.show_target_form && self.get_selected_module().is_some();
+        self.target_message = None;
+        if !self.show_target_form {
+            self.target_form.reset_fields();
+        }
+    }
+
+    /// Work out what the selected module needs to reach the target in the
+    /// target form, keeping the form open to try other targets
+    pub fn submit_target_form(&mut self) {
+        let text = self.target_form.get_fields()[0]
+            .get_internal_value()
+            .try_get_text_value()
+            .unwrap()
+            .trim()
+            .to_owned();
+        let target = match Classification::parse(&text) {
+            Some(classification) => Some(classification.get_boundary()),
+            None => text
+                .trim_end_matches('%')
+                .parse::<f32>()
+                .ok()
+                .filter(|t| (0. ..=100.).contains(t)),
+        };
+        let (target, module) = match (target, self.get_selected_module()) {
+            (Some(target), Some(module)) => (target, module),
+            _ => {
+                self.target_message = Some(Span::styled(
+                    "Enter a mark from 0 to 100 or a classification",
+                    AppStyles::Expense.get(),
+                ));
+                return;
+            }
+        };
+        self.target_message = Some(match required_average(module, target) {
+            RequiredAverage::Reached => Span::styled(
+                format!("{:.2}% is already reached", target),
+                AppStyles::Income.get(),
+            ),
+            RequiredAverage::Needed {
+                average,
+                remaining_weight,
+            } => Span::styled(
+                format!(
+                    "Needs an average of {:.2}% on the unmarked assessments (weight {:.2}) to reach {:.2}%",
+                    average, remaining_weight, target
+                ),
+                AppStyles::Main.get(),
+            ),
+            RequiredAverage::Impossible { best } => Span::styled(
+                format!(
+                    "{:.2}% is impossible, the best still possible is {:.2}%",
+                    target, best
+                ),
+                AppStyles::Expense.get(),
+            ),
+        });
+    }
+
+    pub fn toggle_delete_popup(&mut self) {
+        self.show_delete_popup = !self.show_delete_popup && self.get_selected_module().is_some();
+    }
+
+    /// Delete the selected assessment, or the selected module with all of its
+    /// assessments when none is selected
+    pub async fn delete_selected(&mut self, db: &mut DB) -> Result<(), DbError> {
+        self.show_delete_popup = false;
+        if let Some(grade) = self.get_selected_grade() {
+            db.delete_grade(grade.id).await?;
+        } else if let Some(module) = self.get_selected_module() {
+            db.delete_module(module.id).await?;
+            self.selected = self.selected.saturating_sub(1);
+        }
+        self.refresh(db).await
     }
 }
 
@@ -135,8 +695,34 @@ impl StatefulWidget for GradeTracker {
     type State = GradeTrackerState;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        render_year_header(area, buf, state);
+        let area = Rect {
+            y: area.y + 1,
+            height: area.height - 1,
+            ..area
+        };
+        let modules = state.get_year_modules();
+        if modules.is_empty() {
+            draw_rect_borders(
+                buf,
+                area,
+                Borders::ALL,
+                BorderType::Plain,
+                AppStyles::Accent.get(),
+            );
+            buf.set_string(
+                area.x + 1,
+                area.y + 1,
+                "No modules this year",
+                AppStyles::Accent.get(),
+            );
+            return;
+        }
         let min_height: u16 = 3;
-        let tall_height = area.height - min_height * (state.data.len() - 1) as u16;
+        let tall_height = area
+            .height
+            .saturating_sub(min_height * (modules.len() - 1) as u16)
+            .max(min_height);
         let bar_height = 3;
         let num_of_columns = 2;
         // -2 for side borders, other - for bar side borders
@@ -144,23 +730,25 @@ impl StatefulWidget for GradeTracker {
         // -1 for module name first line
         let num_of_rows = (tall_height - 1) / bar_height;
 
-        for (i, module) in state.data.iter().enumerate() {
+        for (i, module) in modules.iter().enumerate() {
             let i = i as u16;
 
+            // only marked work counts towards the statistics
+            let marked_count = module.marked_grades().count();
+
             // calculate total percentage
             let total_percent: f32 = module
-                .grades
-                .iter()
-                .map(|g| g.percentage * (g.weight / 100.0))
+                .marked_grades()
+                .map(|(g, p)| p * (g.weight / 100.0))
                 .sum();
+            let total_percent = (marked_count > 0).then_some(total_percent);
 
             // average
-            let mean_avg: f32 = module.grades.iter().map(|g| g.percentage).sum();
-            let mean_avg = mean_avg / module.grades.len() as f32;
+            let mean_avg: f32 = module.marked_grades().map(|(_, p)| p).sum();
+            let mean_avg = (marked_count > 0).then(|| mean_avg / marked_count as f32);
 
             // weighted average
-            let weighted_avg: f32 = module.grades.iter().map(|g| g.weight).sum();
-            let weighted_avg = total_percent / (weighted_avg / 100.0);
+            let weighted_avg = module.mark();
 
             let oy = if i > state.selected as u16 {
                 tall_height + (i - 1) * min_height
@@ -177,7 +765,7 @@ impl StatefulWidget for GradeTracker {
                 height: if is_selected { tall_height } else { min_height },
             };
 
-            let (ox, oy) = (area.x + 1, oy + 1);
+            let (ox, oy) = (area.x + 1, area.y + oy + 1);
 
             // draw border
             draw_rect_borders(
@@ -192,31 +780,25 @@ impl StatefulWidget for GradeTracker {
                 },
             );
             // draw module name
-            buf.set_string(
+            let (x, _) = buf.set_stringn(
                 ox,
                 oy,
                 &module.name,
+                rect.width as usize - 2,
                 if is_selected {
                     AppStyles::TitleText.get()
                 } else {
                     AppStyles::TitleTextDeactivated.get()
                 },
             );
-            let total_text = format!(
-                "Overall: {}.{}%",
-                total_percent.trunc(),
-                (total_percent.fract() * 100.0).round()
-            );
-            let mean_text = format!(
-                "Mean: {}.{}%",
-                mean_avg.trunc(),
-                (mean_avg.fract() * 100.0).round()
-            );
-            let weighted_text = format!(
-                "Weighted: {}.{}%",
-                weighted_avg.trunc(),
-                (weighted_avg.fract() * 100.0).round()
-            );
+            let details = match &module.term {
+                Some(term) => format!(" {}, {} credits", term, module.credits),
+                None => format!(" {} credits", module.credits),
+            };
+            buf.set_string(x, oy, details, AppStyles::Accent.get());
+            let total_text = format!("Overall: {}", format_percent(total_percent));
+            let mean_text = format!("Mean: {}", format_percent(mean_avg));
+            let weighted_text = format!("Weighted: {}", format_percent(weighted_avg));
             let stats_style = if is_selected {
                 AppStyles::Main.get()
             } else {
@@ -251,6 +833,13 @@ impl StatefulWidget for GradeTracker {
             // draw module grades
             if is_selected {
                 for (j, grade) in module.grades.iter().enumerate() {
+                    let bar_selected = state.selected_grade == Some(j);
+                    // work not marked yet is drawn dimmed and without a bar
+                    let bar_style = if grade.percentage.is_some() {
+                        AppStyles::Main.get()
+                    } else {
+                        AppStyles::Accent.get()
+                    };
                     let j = j as u16;
                     let bar_rect = Rect {
                         x: ox + bar_width * (j / num_of_rows),
@@ -262,59 +851,244 @@ impl StatefulWidget for GradeTracker {
                         buf,
                         bar_rect,
                         Borders::ALL,
-                        BorderType::Plain,
-                        AppStyles::Main.get(),
+                        if bar_selected {
+                            BorderType::Thick
+                        } else {
+                            BorderType::Plain
+                        },
+                        bar_style,
                     );
-                    let highlighted_rect = Rect {
-                        x: bar_rect.x + 1,
-                        y: bar_rect.y + 1,
-                        width: bar_rect.width
-                            - 2
-                            - ((bar_width as f32 - 2.0) * ((100.0 - grade.percentage) / 100.0))
-                                as u16,
-                        height: bar_rect.height - 2,
+                    buf.set_string(bar_rect.x + 1, bar_rect.y + 1, &grade.name, bar_style);
+                    let percent_text = match (grade.percentage, grade.due_date) {
+                        (Some(percentage), _) => format!("{}%", percentage),
+                        (None, Some(due_date)) => format!("due {}", due_date.format("%d %b %Y")),
+                        (None, None) => "pending".to_owned(),
                     };
-                    buf.set_string(
-                        bar_rect.x + 1,
-                        bar_rect.y + 1,
-                        &grade.name,
-                        AppStyles::Main.get(),
-                    );
-                    let percent_text = format!("{}%", grade.percentage);
                     buf.set_string(
                         bar_rect.x - 1 + bar_rect.width - percent_text.len() as u16,
                         bar_rect.y + 1,
                         percent_text,
-                        AppStyles::Main.get(),
+                        bar_style,
                     );
-                    buf.set_style(highlighted_rect, AppStyles::InvertedMain.get());
+                    if let Some(percentage) = grade.percentage {
+                        let highlighted_rect = Rect {
+                            x: bar_rect.x + 1,
+                            y: bar_rect.y + 1,
+                            width: bar_rect.width
+                                - 2
+                                - ((bar_width as f32 - 2.0) * ((100.0 - percentage) / 100.0))
+                                    as u16,
+                            height: bar_rect.height - 2,
+                        };
+                        buf.set_style(highlighted_rect, AppStyles::InvertedMain.get());
+                    }
                 }
             }
         }
+        if state.show_module_form {
+            render_form_popup(
+                centered_rect(40, 60, area),
+                buf,
+                if state.editing_module.is_some() {
+                    " Edit Module "
+                } else {
+                    " New Module "
+                },
+                &mut state.module_form,
+                None,
+            );
+        }
         if state.show_form {
-            let area = centered_rect(50, 50, area);
-            clear_area(buf, area);
-            draw_rect_borders(
+            let error = state
+                .form_error
+                .as_ref()
+                .map(|e| Span::styled(e.clone(), AppStyles::Expense.get()));
+            render_form_popup(
+                centered_rect(50, 60, area),
                 buf,
-                area,
-                Borders::ALL,
-                BorderType::Thick,
-                AppStyles::Main.get(),
+                if state.editing_grade.is_some() {
+                    " Edit Assessment "
+                } else {
+                    " Enter New Assessment "
+                },
+                &mut state.form_state,
+                error,
             );
-            let title_text = " Enter New Assessment ";
-            buf.set_string(
-                area.x + ((area.width - 2) / 2) - (title_text.len() as u16 / 2),
-                area.y,
-                title_text,
-                AppStyles::Main.get(),
+        }
+        if state.show_target_form {
+            let title = format!(
+                " Target for {} ",
+                state.get_selected_module().map_or("", |m| m.name.as_str())
             );
-            let area = Rect {
-                x: area.x + 1,
-                y: area.y + 1,
-                width: area.width - 2,
-                height: area.height - 2,
-            };
-            Form.render(area, buf, &mut state.form_state);
+            render_form_popup(
+                centered_rect(50, 40, area),
+                buf,
+                &title,
+                &mut state.target_form,
+                state.target_message.clone(),
+            );
+        }
+        if state.show_delete_popup {
+            render_delete_popup(area, buf, state);
         }
     }
 }
+
+/// Confirm deleting the selected assessment, or the selected module when no
+/// assessment is selected
+fn render_delete_popup(area: Rect, buf: &mut Buffer, state: &GradeTrackerState) {
+    let (question, name) = match (state.get_selected_grade(), state.get_selected_module()) {
+        (Some(grade), _) => ("Delete assessment?".to_owned(), grade.name.clone()),
+        (None, Some(module)) => (
+            format!("Delete module and its {} assessments?", module.grades.len()),
+            module.name.clone(),
+        ),
+        (None, None) => return,
+    };
+    let lines = vec![
+        Spans::from(Span::styled(question, AppStyles::TitleText.get())),
+        Spans::from(""),
+        Spans::from(Span::styled(name, AppStyles::Main.get())),
+        Spans::from(""),
+        Spans::from(Span::styled("(y)es / (n)o", AppStyles::Accent.get())),
+    ];
+    let p = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: false });
+    Popup::new(p, 40, 30).render(area, buf);
+}
+
+/// A module statistic, or `-` for a module without any assessments
+fn format_percent(percent: Option<f32>) -> String {
+    match percent {
+        Some(percent) => format!("{:.2}%", percent),
+        None => "-".to_owned(),
+    }
+}
+
+/// Tabs for each year with the shown year's average and the degree's so far
+fn render_year_header(area: Rect, buf: &mut Buffer, state: &GradeTrackerState) {
+    let mut x = area.x;
+    for year in state.get_years() {
+        let style = if year == state.year {
+            AppStyles::TitleText.get()
+        } else {
+            AppStyles::TitleTextDeactivated.get()
+        };
+        (x, _) = buf.set_stringn(
+            x,
+            area.y,
+            format!(" Year {} ", year),
+            (area.x + area.width).saturating_sub(x) as usize,
+            style,
+        );
+    }
+
+    let format_average = |average: Option<f32>| match average {
+        Some(average) => format!(
+            "{:.2}% ({})",
+            average,
+            Classification::from_mark(average).get_name()
+        ),
+        None => "-".to_owned(),
+    };
+    let text = format!(
+        "Year {}: {}  Degree: {}",
+        state.year,
+        format_average(year_average(state.get_year_modules())),
+        format_average(degree_average(&state.data, &CONFIG.grades.year_weights))
+    );
+    buf.set_stringn(
+        (area.x + area.width).saturating_sub(text.chars().count() as u16 + 1),
+        area.y,
+        text,
+        area.width as usize,
+        AppStyles::Main.get(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(year: u32, credits: u32, marks: &[f32]) -> Module {
+        let mut module = Module::new("Module".to_owned(), year);
+        module.credits = credits;
+        module.grades = marks
+            .iter()
+            .map(|mark| Grade::new("Exam".to_owned(), Some(*mark), 50.))
+            .collect();
+        module
+    }
+
+    #[test]
+    fn test_degree_average() {
+        let modules = vec![
+            module(1, 20, &[40.]),
+            module(2, 40, &[70.]),
+            module(2, 20, &[50., 60.]),
+            module(2, 20, &[]),
+            module(3, 10, &[80.]),
+        ];
+        let year_two = modules.iter().filter(|m| m.year == 2);
+        assert_eq!(year_average(year_two), Some(65.));
+        assert_eq!(year_average(&[]), None);
+
+        // the first year doesn't count
+        assert_eq!(degree_average(&modules, &[0., 40., 60.]), Some(74.));
+        assert_eq!(degree_average(&modules, &[1.]), Some(40.));
+        assert_eq!(degree_average(&modules[..1], &[0., 1.]), None);
+
+        assert_eq!(Classification::from_mark(74.), Classification::First);
+        assert_eq!(
+            Classification::from_mark(69.99),
+            Classification::UpperSecond
+        );
+        assert_eq!(Classification::from_mark(12.), Classification::Fail);
+    }
+
+    #[test]
+    fn test_required_average() {
+        let mut module = Module::new("Module".to_owned(), 3);
+        module.grades = vec![
+            Grade::new("Quiz".to_owned(), Some(80.), 20.),
+            Grade::new("Project".to_owned(), Some(50.), 40.),
+            Grade::new("Exam".to_owned(), None, 40.),
+        ];
+        // 36% of the module is done, 40% of it left
+        assert_eq!(
+            required_average(&module, 56.),
+            RequiredAverage::Needed {
+                average: 50.,
+                remaining_weight: 40.
+            }
+        );
+        assert_eq!(required_average(&module, 30.), RequiredAverage::Reached);
+        assert_eq!(
+            required_average(&module, 80.),
+            RequiredAverage::Impossible { best: 76. }
+        );
+        // weights are relative to each other rather than out of 100
+        module.grades = vec![
+            Grade::new("Quiz".to_owned(), Some(80.), 1.),
+            Grade::new("Exam".to_owned(), None, 3.),
+        ];
+        assert_eq!(
+            required_average(&module, 65.),
+            RequiredAverage::Needed {
+                average: 60.,
+                remaining_weight: 3.
+            }
+        );
+        assert_eq!(required_average(&module, 20.), RequiredAverage::Reached);
+        assert_eq!(
+            required_average(&module, 96.),
+            RequiredAverage::Impossible { best: 95. }
+        );
+        assert_eq!(Classification::parse(" first"), Some(Classification::First));
+        assert_eq!(
+            Classification::parse("2:2").map(|c| c.get_boundary()),
+            Some(50.)
+        );
+    }
+}
@@ -0,0 +1,390 @@
+use std::{fmt, fs, io};
+
+use chrono::{NaiveDate, NaiveTime, TimeZone, Utc};
+
+use crate::{
+    config::{CsvImportConfig, CONFIG},
+    db::{ExchangeRate, MoneyTransaction, TransactionKind},
+    money::Money,
+    util::expand_home,
+};
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(io::Error),
+    /// A record that couldn't be read, `record` counts from 1 including any
+    /// header
+    Parse {
+        record: usize,
+        message: String,
+    },
+    UnknownFormat(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(e) => write!(f, "Could not read file: {}", e),
+            ImportError::Parse { record, message } => {
+                write!(f, "Invalid record {}: {}", record, message)
+            }
+            ImportError::UnknownFormat(ext) => {
+                write!(f, "Unknown file type '{}', expected csv, ofx or qfx", ext)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<io::Error> for ImportError {
+    fn from(e: io::Error) -> Self {
+        ImportError::Io(e)
+    }
+}
+
+/// Read a bank statement, choosing the format from the file extension
+///
+/// CSV files use the column mapping from the config, OFX and QFX files are
+/// read from their `STMTTRN` blocks
+pub fn import_file(path: &str) -> Result<Vec<MoneyTransaction>, ImportError> {
+    let path = expand_home(path.trim());
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let text = fs::read_to_string(&path)?;
+    match extension.as_str() {
+        "csv" => parse_csv(&text, &CONFIG.csv_import),
+        "ofx" | "qfx" => parse_ofx(&text),
+        _ => Err(ImportError::UnknownFormat(extension)),
+    }
+}
+
+/// Turn a signed amount into a transaction, negative amounts are expenses
+fn new_transaction(
+    title: String,
+    amount: Money,
+    details: String,
+    date: NaiveDate,
+) -> MoneyTransaction {
+    let kind = if amount.minor() < 0 {
+        TransactionKind::Expense
+    } else {
+        TransactionKind::Income
+    };
+    MoneyTransaction::new(
+        title,
+        amount.abs(),
+        details,
+        Utc.from_utc_datetime(&date.and_time(NaiveTime::MIN)),
+        kind,
+        None,
+    )
+}
+
+/// Parse an amount as written in a statement, e.g. `-1,234.50` or `£3.20`,
+/// rounding any extra decimal places such as the `5` in `-12.345`
+fn parse_amount(text: &str) -> Option<Money> {
+    let cleaned = text
+        .trim()
+        .replace(',', "")
+        .replace(&CONFIG.currency.symbol, "");
+    Money::parse_rounded(&cleaned)
+}
+
+// CSV ------------------------------------------------------------------------
+
+pub fn parse_csv(
+    text: &str,
+    mapping: &CsvImportConfig,
+) -> Result<Vec<MoneyTransaction>, ImportError> {
+    let records = split_csv_records(text, mapping.delimiter);
+    let skip = if mapping.has_header { 1 } else { 0 };
+    let mut transactions = vec![];
+    for (i, record) in records.iter().enumerate().skip(skip) {
+        // blank lines
+        if record.iter().all(|f| f.trim().is_empty()) {
+            continue;
+        }
+        let parse_error = |message: String| ImportError::Parse {
+            record: i + 1,
+            message,
+        };
+        let field = |column: usize| {
+            record
+                .get(column)
+                .map(|f| f.trim())
+                .ok_or_else(|| parse_error(format!("missing column {}", column)))
+        };
+
+        let date_text = field(mapping.date_column)?;
+        let date = NaiveDate::parse_from_str(date_text, &mapping.date_format)
+            .map_err(|_| parse_error(format!("invalid date '{}'", date_text)))?;
+        let amount = |text: &str| {
+            parse_amount(text).ok_or_else(|| parse_error(format!("invalid amount '{}'", text)))
+        };
+        let amount_text = field(mapping.amount_column)?;
+        let amount = match mapping.credit_column {
+            // separate columns for money out and money in, one is left empty
+            Some(credit_column) => match (amount_text, field(credit_column)?) {
+                ("", "") => return Err(parse_error("no amount".to_owned())),
+                (debit, "") => -amount(debit)?.abs(),
+                (_, credit) => amount(credit)?,
+            },
+            None => amount(amount_text)?,
+        };
+        let details = match mapping.details_column {
+            Some(column) => field(column)?.to_owned(),
+            None => "".to_owned(),
+        };
+        transactions.push(new_transaction(
+            field(mapping.title_column)?.to_owned(),
+            amount,
+            details,
+            date,
+        ));
+    }
+    Ok(transactions)
+}
+
+/// Split CSV text into records of fields, handling quoted fields containing
+/// delimiters, newlines and doubled quotes
+fn split_csv_records(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => record.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {}
+            '\n' if !in_quotes => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+/// Read exchange rates from a CSV file of `currency,date,rate` records, with
+/// dates as `YYYY-MM-DD` and an optional header
+pub fn import_exchange_rates(path: &str) -> Result<Vec<ExchangeRate>, ImportError> {
+    let text = fs::read_to_string(expand_home(path.trim()))?;
+    parse_exchange_rates(&text)
+}
+
+pub fn parse_exchange_rates(text: &str) -> Result<Vec<ExchangeRate>, ImportError> {
+    let mut rates = vec![];
+    for (i, record) in split_csv_records(text, ',').iter().enumerate() {
+        let fields: Vec<&str> = record.iter().map(|f| f.trim()).collect();
+        let parse_error = |message: String| ImportError::Parse {
+            record: i + 1,
+            message,
+        };
+        match fields.as_slice() {
+            [currency, ..] if i == 0 && currency.eq_ignore_ascii_case("currency") => {}
+            [currency, date, rate] => {
+                let currency = parse_currency_code(currency)
+                    .ok_or_else(|| parse_error(format!("invalid currency '{}'", currency)))?;
+                let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|_| parse_error(format!("invalid date '{}'", date)))?;
+                let rate = rate
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|r| *r > 0.)
+                    .ok_or_else(|| parse_error(format!("invalid rate '{}'", rate)))?;
+                rates.push(ExchangeRate {
+                    currency,
+                    date,
+                    rate,
+                });
+            }
+            _ if fields.iter().all(|f| f.is_empty()) => {}
+            _ => {
+                return Err(parse_error(format!(
+                    "expected 3 columns, found {}",
+                    fields.len()
+                )))
+            }
+        }
+    }
+    Ok(rates)
+}
+
+/// Upper case three letter currency code such as `USD`
+pub fn parse_currency_code(text: &str) -> Option<String> {
+    let code = text.trim().to_uppercase();
+    (code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic())).then_some(code)
+}
+
+// OFX ------------------------------------------------------------------------
+
+/// Parse the transactions from an OFX or QFX statement
+///
+/// Works with both the SGML (v1) and XML (v2) forms as closing tags on values
+/// are optional
+pub fn parse_ofx(text: &str) -> Result<Vec<MoneyTransaction>, ImportError> {
+    // only tag names need to match case insensitively, and ASCII upper casing
+    // keeps byte offsets the same as in `text`
+    let upper = text.to_ascii_uppercase();
+    let mut transactions = vec![];
+    let mut search_from = 0;
+    while let Some(start) = upper[search_from..].find("<STMTTRN>") {
+        let start = search_from + start + "<STMTTRN>".len();
+        let end = upper[start..]
+            .find("</STMTTRN>")
+            .map_or(text.len(), |e| start + e);
+        let block = &text[start..end];
+        search_from = end;
+
+        let parse_error = |message: String| ImportError::Parse {
+            record: transactions.len() + 1,
+            message,
+        };
+        let date_text = ofx_value(block, "DTPOSTED")
+            .ok_or_else(|| parse_error("missing DTPOSTED".to_owned()))?;
+        // dates are YYYYMMDD followed by an optional time and timezone
+        let date = date_text
+            .get(..8)
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok())
+            .ok_or_else(|| parse_error(format!("invalid date '{}'", date_text)))?;
+        let amount_text =
+            ofx_value(block, "TRNAMT").ok_or_else(|| parse_error("missing TRNAMT".to_owned()))?;
+        let amount = parse_amount(amount_text)
+            .ok_or_else(|| parse_error(format!("invalid amount '{}'", amount_text)))?;
+        let memo = ofx_value(block, "MEMO").unwrap_or("");
+        let (title, details) = match ofx_value(block, "NAME") {
+            Some(name) => (name, memo),
+            None => (memo, ""),
+        };
+        transactions.push(new_transaction(
+            unescape_ofx(title),
+            amount,
+            unescape_ofx(details),
+            date,
+        ));
+    }
+    Ok(transactions)
+}
+
+/// Get the value of a tag in an OFX block, up to the next tag or line end
+fn ofx_value<'a>(block: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let start = block.to_ascii_uppercase().find(&open)? + open.len();
+    let rest = &block[start..];
+    let end = rest.find(['<', '\n', '\r']).unwrap_or(rest.len());
+    Some(rest[..end].trim())
+}
+
+fn unescape_ofx(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv() {
+        let text = "Date,Description,Amount,Notes\r\n\
+            14/07/2023,\"Shop, Ltd\",-12.50,\"said \"\"hi\"\"\"\r\n\
+            \r\n\
+            15/07/2023,Salary,\"1,000.00\",\n";
+        let mapping = CsvImportConfig {
+            details_column: Some(3),
+            ..CsvImportConfig::default()
+        };
+        let transactions = parse_csv(text, &mapping).unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].title, "Shop, Ltd");
+        assert_eq!(transactions[0].details, "said \"hi\"");
+        assert_eq!(transactions[0].amount.minor(), 1250);
+        assert_eq!(transactions[0].kind, TransactionKind::Expense);
+        assert_eq!(transactions[1].amount.minor(), 100000);
+        assert_eq!(transactions[1].kind, TransactionKind::Income);
+        assert_eq!(
+            transactions[1].date.date_naive(),
+            NaiveDate::from_ymd_opt(2023, 7, 15).unwrap()
+        );
+
+        // separate money out and money in columns
+        let mapping = CsvImportConfig {
+            has_header: false,
+            date_format: "%Y-%m-%d".to_owned(),
+            credit_column: Some(3),
+            ..CsvImportConfig::default()
+        };
+        let transactions = parse_csv("2023-07-14,Refund,,4.00\n", &mapping).unwrap();
+        assert_eq!(transactions[0].signed_amount().minor(), 400);
+        assert!(matches!(
+            parse_csv("2023-07-14,Bad,x,\n", &mapping),
+            Err(ImportError::Parse { record: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_ofx() {
+        let text = "OFXHEADER:100\n<OFX><BANKMSGSRSV1><STMTTRNRS><STMTRS><BANKTRANLIST>\n\
+            <STMTTRN>\n<TRNTYPE>DEBIT\n<DTPOSTED>20230714120000[0:GMT]\n<TRNAMT>-3.20\n\
+            <FITID>1\n<NAME>Caf&eacute; &amp; Co\n<MEMO>card payment\n</STMTTRN>\n\
+            <stmttrn><trntype>CREDIT</trntype><dtposted>20230715</dtposted>\
+            <trnamt>25.005</trnamt><memo>Transfer</memo></stmttrn>\n\
+            </BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>";
+        let transactions = parse_ofx(text).unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].title, "Caf&eacute; & Co");
+        assert_eq!(transactions[0].details, "card payment");
+        assert_eq!(transactions[0].signed_amount().minor(), -320);
+        assert_eq!(transactions[1].title, "Transfer");
+        // more decimal places than are stored are rounded
+        assert_eq!(transactions[1].signed_amount().minor(), 2501);
+    }
+
+    #[test]
+    fn test_parse_ofx_non_ascii() {
+        // "ß" upper cases to "SS", which would shift offsets after it
+        let text = "<STMTTRN>\n<DTPOSTED>20230714\n<MEMO>Straße Bäckerei\n<TRNAMT>-4.50\n\
+            </STMTTRN>\n<STMTTRN>\n<DTPOSTED>20230715\n<TRNAMT>-1.00\n<MEMO>Maß\n</STMTTRN>";
+        let transactions = parse_ofx(text).unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].title, "Straße Bäckerei");
+        assert_eq!(transactions[0].signed_amount().minor(), -450);
+        assert_eq!(transactions[1].title, "Maß");
+        assert_eq!(transactions[1].signed_amount().minor(), -100);
+    }
+
+    #[test]
+    fn test_parse_exchange_rates() {
+        let rates = parse_exchange_rates("currency,date,rate\nusd,2023-07-01,0.79\n\n").unwrap();
+        assert_eq!(
+            rates,
+            vec![ExchangeRate {
+                currency: "USD".to_owned(),
+                date: NaiveDate::from_ymd_opt(2023, 7, 1).unwrap(),
+                rate: 0.79,
+            }]
+        );
+        assert!(matches!(
+            parse_exchange_rates("EUR,2023-07-01,-1\n"),
+            Err(ImportError::Parse { record: 1, .. })
+        ));
+    }
+}
@@ -6,11 +6,16 @@ use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use db::DB;
-use log::info;
-use screens::Screen;
-use sqlx::Connection;
-use std::{error::Error, future::Future, io, pin::Pin};
+use db::{DbError, DB};
+use log::{error, info, LevelFilter};
+use log4rs::{
+    append::file::FileAppender,
+    config::{Appender, Config, Root},
+    encode::pattern::PatternEncoder,
+};
+use paths::{get_positional_args, PATHS};
+use screens::{error_popup, Screen};
+use std::{error::Error, fs, future::Future, io, panic, pin::Pin, process};
 use tui::{
     backend::{Backend, CrosstermBackend},
     Terminal,
@@ -21,28 +26,57 @@ use wait_popup::WaitPopup;
 mod app;
 mod button;
 mod calendar;
+mod category_rules;
 mod clock;
+mod config;
 mod db;
+mod exchange_rates;
+mod exporter;
 mod film_tracker;
 mod form;
+mod fuzzy_search;
 mod grade_tracker;
+mod importer;
+mod money;
 mod money_tracker;
+mod paths;
 mod popup;
 mod progress_bar;
+mod recurrence;
+mod rules_editor;
 mod screens;
 mod styles;
+mod todo_list;
 mod util;
 mod wait_popup;
 
 use crate::app::App;
 
+type InputFuture<'a> = Pin<Box<dyn Future<Output = Result<(), DbError>> + 'a>>;
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    // setup logging
-    // https://tms-dev-blog.com/log-to-a-file-in-rust-with-log4rs/
-    log4rs::init_file("logging_config.yaml", Default::default()).unwrap();
+    fs::create_dir_all(&PATHS.data_dir)?;
+    init_logging()?;
+
+    DB::create_tables().await?;
 
-    DB::create_tables().await;
+    // `util-tui export <file>` writes out transactions without starting the UI
+    let args = get_positional_args();
+    if args.first().map(String::as_str) == Some("export") {
+        if let Err(err) = export_command(args.get(1)).await {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // put the terminal back to normal before the panic message is printed
+    let default_panic_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        default_panic_hook(info);
+    }));
 
     // setup terminal
     enable_raw_mode()?;
@@ -52,33 +86,85 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let mut terminal = Terminal::new(backend)?;
 
     // create app and run it
-    let app = App::new().await;
-    let res = run_app(&mut terminal, app).await;
+    let res = match App::new().await {
+        Ok(app) => run_app(&mut terminal, app).await,
+        Err(err) => Err(err.into()),
+    };
 
     // restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
+    restore_terminal()?;
     terminal.show_cursor()?;
 
     if let Err(err) = res {
-        println!("{:?}", err)
+        error!("{}", err);
+        println!("{}", err)
     }
 
     Ok(())
 }
 
-async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<()> {
+async fn export_command(path: Option<&String>) -> Result<(), Box<dyn Error>> {
+    let path = path.ok_or(
+        "usage: util-tui export <file.csv|file.json> [--from YYYY-MM-DD] [--to YYYY-MM-DD] \
+        [--keyword TEXT] [--details TEXT] [--min AMOUNT] [--max AMOUNT]",
+    )?;
+    let mut db = DB::new().await?;
+    db.run_migrations().await?;
+    let count = exporter::run_cli(&mut db, path).await;
+    db.close().await?;
+    println!("Exported {} transactions to {}", count?, path);
+    Ok(())
+}
+
+/// Use `logging_config.yaml` from the config directory if there is one,
+/// otherwise log to `log/info.log` in the data directory
+// https://tms-dev-blog.com/log-to-a-file-in-rust-with-log4rs/
+fn init_logging() -> Result<(), Box<dyn Error>> {
+    let config_path = PATHS.get_logging_config_path();
+    if config_path.exists() {
+        log4rs::init_file(config_path, Default::default())?;
+        return Ok(());
+    }
+    let file_logger = FileAppender::builder()
+        .encoder(Box::new(PatternEncoder::new(
+            "{d(%Y-%m-%d %H:%M:%S)(utc)} - {h({l})}: {m}{n}",
+        )))
+        .build(PATHS.get_log_path())?;
+    let config = Config::builder()
+        .appender(Appender::builder().build("file_logger", Box::new(file_logger)))
+        .build(
+            Root::builder()
+                .appender("file_logger")
+                .build(LevelFilter::Info),
+        )?;
+    log4rs::init_config(config)?;
+    Ok(())
+}
+
+fn restore_terminal() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)
+}
+
+async fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    mut app: App,
+) -> Result<(), Box<dyn Error>> {
     loop {
         let c = terminal.draw(|f| {
             app.cur_screen.get_screen_func()(f, &mut app);
+            if let Some(error) = &app.error {
+                error_popup(f, error);
+            }
         })?;
 
         let mut futs = Vec::new();
         if let Event::Key(key) = event::read()? {
+            // any key dismisses the error popup
+            if app.error.is_some() {
+                app.error = None;
+                continue;
+            }
             // quit if read_input returns true
             if read_input(&mut app, &key, &mut futs) {
                 break;
@@ -91,26 +177,54 @@ async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Re
                 f.render_widget(wait_popup, f.size());
             })?;
 
-            // execute all futures from the read_input func
+            // execute all futures from the read_input func, database errors
+            // are shown to the user rather than ending the app
+            let mut errors = Vec::new();
             for f in futs {
-                f.await;
+                if let Err(err) = f.await {
+                    error!("{}", err);
+                    errors.push(err.to_string());
+                }
+            }
+            if !errors.is_empty() {
+                app.error = Some(errors.join("\n"));
             }
         }
     }
-    app.db.conn.close();
-    return Ok(());
+    app.db.close().await?;
+    Ok(())
+}
+
+/// Wrap a future that can't fail so it can be queued with the database ones
+async fn infallible(fut: impl Future<Output = ()>) -> Result<(), DbError> {
+    fut.await;
+    Ok(())
 }
 
-fn read_input<'a>(
-    app: &'a mut App,
-    key: &KeyEvent,
-    futs: &mut Vec<Pin<Box<dyn Future<Output = ()> + 'a>>>,
-) -> bool {
+fn read_input<'a>(app: &'a mut App, key: &KeyEvent, futs: &mut Vec<InputFuture<'a>>) -> bool {
     // https://users.rust-lang.org/t/storing-futures/34564/8
 
     let capture_input = app.grade_state.show_form
+        || app.grade_state.show_module_form
+        || app.grade_state.show_delete_popup
+        || app.grade_state.show_target_form
         || app.money_state.add_form_selected
-        || app.money_state.search_form_selected;
+        || app.money_state.search_form_selected
+        || app.money_state.show_delete_popup
+        || app.money_state.show_budget_form
+        || app.money_state.show_import_form
+        || app.money_state.import_preview.is_some()
+        || app.money_state.show_export_form
+        || app.money_state.show_recurring_form
+        || app.money_state.show_jump_form
+        || app.money_state.show_account_form
+        || app.money_state.show_transfer_form
+        || app.money_state.show_detail_popup
+        || app.money_state.fuzzy_search.is_some()
+        || app.todo_state.show_form
+        || app.rules_state.show_form
+        || app.rates_state.show_form
+        || app.rates_state.show_import_form;
 
     match (&app.cur_screen, key.code, capture_input) {
         // Dashboard Screen ---------------------------------------------------
@@ -133,10 +247,10 @@ fn read_input<'a>(
 
         // Calendar Screen ----------------------------------------------------
         (Screen::CalendarScreen, KeyCode::Down, _) => {
-            futs.push(Box::pin(app.calendar_state.increment_month(-1)));
+            futs.push(Box::pin(infallible(app.calendar_state.increment_month(-1))));
         }
         (Screen::CalendarScreen, KeyCode::Up, _) => {
-            futs.push(Box::pin(app.calendar_state.increment_month(1)));
+            futs.push(Box::pin(infallible(app.calendar_state.increment_month(1))));
         }
         (Screen::CalendarScreen, KeyCode::Left, _) => {
             if app.calendar_state.show_popup {
@@ -155,29 +269,65 @@ fn read_input<'a>(
         (Screen::CalendarScreen, KeyCode::Enter, _) => app.calendar_state.popup_toggle(),
 
         // Grade Screen -------------------------------------------------------
+        (Screen::GradeScreen, KeyCode::Char('y'), true) if app.grade_state.show_delete_popup => {
+            futs.push(Box::pin(app.grade_state.delete_selected(&mut app.db)));
+        }
+        (Screen::GradeScreen, _, true) if app.grade_state.show_delete_popup => {
+            app.grade_state.toggle_delete_popup();
+        }
         (Screen::GradeScreen, KeyCode::Up, false) => app.grade_state.increment_selected(-1),
         (Screen::GradeScreen, KeyCode::Up, true) => {
-            app.grade_state.form_state.increment_selected(-1);
+            app.grade_state.get_active_form_mut().increment_selected(-1);
         }
         (Screen::GradeScreen, KeyCode::Down, false) => app.grade_state.increment_selected(1),
         (Screen::GradeScreen, KeyCode::Down, true) => {
-            app.grade_state.form_state.increment_selected(1);
+            app.grade_state.get_active_form_mut().increment_selected(1);
         }
         (Screen::GradeScreen, KeyCode::Tab, true) => {
-            app.grade_state.form_state.increment_selected(1);
+            app.grade_state.get_active_form_mut().increment_selected(1);
+        }
+        (Screen::GradeScreen, KeyCode::Left, false) => {
+            app.grade_state.increment_selected_grade(-1);
+        }
+        (Screen::GradeScreen, KeyCode::Right, false) => {
+            app.grade_state.increment_selected_grade(1);
+        }
+        (Screen::GradeScreen, KeyCode::Char('['), false) => app.grade_state.increment_year(-1),
+        (Screen::GradeScreen, KeyCode::Char(']'), false) => app.grade_state.increment_year(1),
+        (Screen::GradeScreen, KeyCode::Esc, true) if app.grade_state.show_module_form => {
+            app.grade_state.toggle_module_form();
+        }
+        (Screen::GradeScreen, KeyCode::Esc, true) if app.grade_state.show_target_form => {
+            app.grade_state.toggle_target_form();
         }
         (Screen::GradeScreen, KeyCode::Esc, true) => {
             app.grade_state.toggle_form();
         }
-        (Screen::GradeScreen, KeyCode::Char('i'), false) => {
-            app.grade_state.toggle_form();
+        (Screen::GradeScreen, KeyCode::Char('i'), false) => app.grade_state.open_form(false),
+        (Screen::GradeScreen, KeyCode::Char('e'), false) => app.grade_state.open_form(true),
+        (Screen::GradeScreen, KeyCode::Char('A'), false) => {
+            app.grade_state.open_add_module_form();
+        }
+        (Screen::GradeScreen, KeyCode::Char('E'), false) => {
+            app.grade_state.toggle_module_form();
+        }
+        (Screen::GradeScreen, KeyCode::Char('x'), false) => {
+            app.grade_state.toggle_delete_popup();
+        }
+        (Screen::GradeScreen, KeyCode::Char('T'), false) => {
+            app.grade_state.toggle_target_form();
         }
         (Screen::GradeScreen, KeyCode::Char(_) | KeyCode::Backspace, true) => {
-            app.grade_state.form_state.send_input(&key.code);
+            app.grade_state.get_active_form_mut().send_input(&key.code);
+        }
+        (Screen::GradeScreen, KeyCode::Enter, true) if app.grade_state.show_module_form => {
+            futs.push(Box::pin(app.grade_state.submit_module_form(&mut app.db)));
+        }
+        (Screen::GradeScreen, KeyCode::Enter, true) if app.grade_state.show_target_form => {
+            app.grade_state.submit_target_form();
         }
         (Screen::GradeScreen, KeyCode::Enter, true) => {
-            app.grade_state.submit_form();
-            app.grade_state.toggle_form();
+            futs.push(Box::pin(app.grade_state.submit_form(&mut app.db)));
         }
 
         // Money Screen -------------------------------------------------------
@@ -187,38 +337,205 @@ fn read_input<'a>(
         (Screen::MoneyScreen, KeyCode::Char('s'), false) => {
             app.money_state.select_search_form();
         }
+        (Screen::MoneyScreen, KeyCode::Char('y'), true) if app.money_state.show_delete_popup => {
+            futs.push(Box::pin(
+                app.money_state.delete_selected_transaction(&mut app.db),
+            ));
+        }
+        (Screen::MoneyScreen, _, true) if app.money_state.show_delete_popup => {
+            app.money_state.toggle_delete_popup();
+        }
+        (Screen::MoneyScreen, KeyCode::Enter, false) => {
+            futs.push(Box::pin(app.money_state.open_detail_popup(&mut app.db)));
+        }
+        (Screen::MoneyScreen, KeyCode::Char('/'), false) => {
+            futs.push(Box::pin(app.money_state.open_fuzzy_search(&mut app.db)));
+        }
+        (Screen::MoneyScreen, key_code, true) if app.money_state.fuzzy_search.is_some() => {
+            match key_code {
+                KeyCode::Enter => {
+                    futs.push(Box::pin(app.money_state.submit_fuzzy_search(&mut app.db)));
+                }
+                KeyCode::Esc => app.money_state.close_fuzzy_search(),
+                KeyCode::Up => app.money_state.increment_fuzzy_selected(-1),
+                KeyCode::Down => app.money_state.increment_fuzzy_selected(1),
+                _ => app.money_state.send_fuzzy_input(&key.code),
+            }
+        }
+        (Screen::MoneyScreen, key_code, true) if app.money_state.show_attachment_form => {
+            match key_code {
+                KeyCode::Enter => {
+                    futs.push(Box::pin(
+                        app.money_state.submit_attachment_form(&mut app.db),
+                    ));
+                }
+                KeyCode::Esc => app.money_state.toggle_attachment_form(),
+                _ => app.money_state.attachment_form.send_input(&key.code),
+            }
+        }
+        (Screen::MoneyScreen, key_code, true) if app.money_state.show_detail_popup => {
+            match key_code {
+                KeyCode::Up => app.money_state.increment_selected_attachment(-1),
+                KeyCode::Down => app.money_state.increment_selected_attachment(1),
+                KeyCode::Char('o') => app.money_state.open_selected_attachment(),
+                KeyCode::Char('n') => app.money_state.toggle_attachment_form(),
+                KeyCode::Char('r') => futs.push(Box::pin(
+                    app.money_state.delete_selected_attachment(&mut app.db),
+                )),
+                KeyCode::Char('e') => {
+                    app.money_state.close_detail_popup();
+                    app.money_state.edit_selected_transaction();
+                }
+                KeyCode::Char('d') => {
+                    app.money_state.close_detail_popup();
+                    app.money_state.duplicate_selected_transaction();
+                }
+                KeyCode::Char('x') => {
+                    app.money_state.close_detail_popup();
+                    app.money_state.toggle_delete_popup();
+                }
+                KeyCode::Esc => app.money_state.close_detail_popup(),
+                _ => {}
+            }
+        }
+        (Screen::MoneyScreen, KeyCode::Char('I'), false) => {
+            app.money_state.toggle_import_form();
+        }
+        (Screen::MoneyScreen, KeyCode::Esc, true) if app.money_state.show_import_form => {
+            app.money_state.toggle_import_form();
+        }
+        (Screen::MoneyScreen, KeyCode::Enter, true) if app.money_state.show_import_form => {
+            futs.push(Box::pin(app.money_state.load_import(&mut app.db)));
+        }
+        (Screen::MoneyScreen, key_code, true) if app.money_state.import_preview.is_some() => {
+            match key_code {
+                KeyCode::Enter => futs.push(Box::pin(app.money_state.confirm_import(&mut app.db))),
+                KeyCode::Esc => app.money_state.cancel_import(),
+                _ => {
+                    let preview = app.money_state.import_preview.as_mut().unwrap();
+                    match key_code {
+                        KeyCode::Up => preview.increment_selected(-1),
+                        KeyCode::Down => preview.increment_selected(1),
+                        KeyCode::Char(' ') => preview.toggle_selected(),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        (Screen::MoneyScreen, KeyCode::Char('E'), false) => {
+            app.money_state.toggle_export_form();
+        }
+        (Screen::MoneyScreen, KeyCode::Esc, true) if app.money_state.show_export_form => {
+            app.money_state.toggle_export_form();
+        }
+        (Screen::MoneyScreen, KeyCode::Enter, true) if app.money_state.show_export_form => {
+            futs.push(Box::pin(app.money_state.submit_export_form(&mut app.db)));
+        }
+        (Screen::MoneyScreen, KeyCode::Char('b'), false) => {
+            app.money_state.toggle_budget_form();
+        }
+        (Screen::MoneyScreen, KeyCode::Esc, true) if app.money_state.show_budget_form => {
+            app.money_state.toggle_budget_form();
+        }
+        (Screen::MoneyScreen, KeyCode::Enter, true) if app.money_state.show_budget_form => {
+            futs.push(Box::pin(app.money_state.submit_budget_form(&mut app.db)));
+        }
+        (Screen::MoneyScreen, KeyCode::Char('S'), false) => {
+            app.money_state.toggle_subscriptions();
+        }
+        (Screen::MoneyScreen, KeyCode::Char('n'), false) if app.money_state.show_subscriptions => {
+            app.money_state.toggle_recurring_form();
+        }
+        (Screen::MoneyScreen, KeyCode::Char('x'), false) if app.money_state.show_subscriptions => {
+            futs.push(Box::pin(
+                app.money_state.delete_selected_recurring(&mut app.db),
+            ));
+        }
+        (Screen::MoneyScreen, KeyCode::Up, false) if app.money_state.show_subscriptions => {
+            app.money_state.increment_selected_recurring(-1);
+        }
+        (Screen::MoneyScreen, KeyCode::Down, false) if app.money_state.show_subscriptions => {
+            app.money_state.increment_selected_recurring(1);
+        }
+        (Screen::MoneyScreen, KeyCode::Esc, true) if app.money_state.show_recurring_form => {
+            app.money_state.toggle_recurring_form();
+        }
+        (Screen::MoneyScreen, KeyCode::Enter, true) if app.money_state.show_recurring_form => {
+            futs.push(Box::pin(app.money_state.submit_recurring_form(&mut app.db)));
+        }
+        (Screen::MoneyScreen, KeyCode::Char('j'), false) => {
+            app.money_state.toggle_jump_form();
+        }
+        (Screen::MoneyScreen, KeyCode::Esc, true) if app.money_state.show_jump_form => {
+            app.money_state.toggle_jump_form();
+        }
+        (Screen::MoneyScreen, KeyCode::Enter, true) if app.money_state.show_jump_form => {
+            futs.push(Box::pin(app.money_state.submit_jump_form(&mut app.db)));
+        }
+        (Screen::MoneyScreen, KeyCode::Char('a'), false) => {
+            futs.push(Box::pin(app.money_state.cycle_account(&mut app.db)));
+        }
+        (Screen::MoneyScreen, KeyCode::Char('A'), false) => {
+            app.money_state.toggle_account_form();
+        }
+        (Screen::MoneyScreen, KeyCode::Esc, true) if app.money_state.show_account_form => {
+            app.money_state.toggle_account_form();
+        }
+        (Screen::MoneyScreen, KeyCode::Enter, true) if app.money_state.show_account_form => {
+            futs.push(Box::pin(app.money_state.submit_account_form(&mut app.db)));
+        }
+        (Screen::MoneyScreen, KeyCode::Char('T'), false) => {
+            app.money_state.toggle_transfer_form();
+        }
+        (Screen::MoneyScreen, KeyCode::Esc, true) if app.money_state.show_transfer_form => {
+            app.money_state.toggle_transfer_form();
+        }
+        (Screen::MoneyScreen, KeyCode::Enter, true) if app.money_state.show_transfer_form => {
+            futs.push(Box::pin(app.money_state.submit_transfer_form(&mut app.db)));
+        }
+        (Screen::MoneyScreen, KeyCode::Char('R'), false) => {
+            app.cur_screen = Screen::RulesScreen;
+            futs.push(Box::pin(app.rules_state.refresh(&mut app.db)));
+        }
+        (Screen::MoneyScreen, KeyCode::Char('X'), false) => {
+            app.cur_screen = Screen::RatesScreen;
+            futs.push(Box::pin(app.rates_state.refresh(&mut app.db)));
+        }
+        (Screen::MoneyScreen, KeyCode::Char('v'), false) => {
+            futs.push(Box::pin(app.money_state.toggle_stats(&mut app.db)));
+        }
+        (Screen::MoneyScreen, KeyCode::Char('p'), false) if app.money_state.show_stats => {
+            futs.push(Box::pin(app.money_state.cycle_stats_period(&mut app.db)));
+        }
+        (Screen::MoneyScreen, KeyCode::Char('e'), false) => {
+            app.money_state.edit_selected_transaction();
+        }
+        (Screen::MoneyScreen, KeyCode::Char('x'), false) => {
+            app.money_state.toggle_delete_popup();
+        }
+        (Screen::MoneyScreen, KeyCode::Char('o'), false) => {
+            futs.push(Box::pin(app.money_state.cycle_sort(&mut app.db)));
+        }
         (Screen::MoneyScreen, KeyCode::Esc, true) => {
             app.money_state.select_transaction_list();
         }
-        (Screen::MoneyScreen, KeyCode::Char(_) | KeyCode::Backspace, true) => {
-            if app.money_state.search_form_selected {
-                app.money_state.search_form.send_input(&key.code);
-            } else {
-                app.money_state.add_form.send_input(&key.code);
-            }
+        (
+            Screen::MoneyScreen,
+            KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Left | KeyCode::Right,
+            true,
+        ) => {
+            app.money_state.get_active_form_mut().send_input(&key.code);
         }
         (Screen::MoneyScreen, KeyCode::Up, false) => app.money_state.increment_selected(-1),
         (Screen::MoneyScreen, KeyCode::Up, true) => {
-            if app.money_state.search_form_selected {
-                app.money_state.search_form.increment_selected(-1);
-            } else {
-                app.money_state.add_form.increment_selected(-1);
-            }
+            app.money_state.get_active_form_mut().increment_selected(-1);
         }
         (Screen::MoneyScreen, KeyCode::Down, false) => app.money_state.increment_selected(1),
         (Screen::MoneyScreen, KeyCode::Down, true) => {
-            if app.money_state.search_form_selected {
-                app.money_state.search_form.increment_selected(1);
-            } else {
-                app.money_state.add_form.increment_selected(1);
-            }
+            app.money_state.get_active_form_mut().increment_selected(1);
         }
         (Screen::MoneyScreen, KeyCode::Tab, true) => {
-            if app.money_state.search_form_selected {
-                app.money_state.search_form.increment_selected(1);
-            } else {
-                app.money_state.add_form.increment_selected(1);
-            }
+            app.money_state.get_active_form_mut().increment_selected(1);
         }
         (Screen::MoneyScreen, KeyCode::Enter, true) => {
             if app.money_state.search_form_selected {
@@ -233,10 +550,132 @@ fn read_input<'a>(
         (Screen::MoneyScreen, KeyCode::Left, false) => {
             futs.push(Box::pin(app.money_state.get_prev_page(&mut app.db)));
         }
+        (Screen::MoneyScreen, KeyCode::Home, false) => {
+            futs.push(Box::pin(app.money_state.get_first_page(&mut app.db)));
+        }
+        (Screen::MoneyScreen, KeyCode::End, false) => {
+            futs.push(Box::pin(app.money_state.get_last_page(&mut app.db)));
+        }
 
         // Film Screen --------------------------------------------------------
         (Screen::FilmScreen, KeyCode::Enter, false) => {
-            futs.push(Box::pin(app.film_state.search_movie("dark".to_owned())));
+            futs.push(Box::pin(infallible(
+                app.film_state.search_movie("dark".to_owned()),
+            )));
+        }
+
+        // Todo Screen --------------------------------------------------------
+        (Screen::TodoScreen, KeyCode::Up, false) => app.todo_state.increment_selected(-1),
+        (Screen::TodoScreen, KeyCode::Up, true) => {
+            app.todo_state.form_state.increment_selected(-1);
+        }
+        (Screen::TodoScreen, KeyCode::Down, false) => app.todo_state.increment_selected(1),
+        (Screen::TodoScreen, KeyCode::Down | KeyCode::Tab, true) => {
+            app.todo_state.form_state.increment_selected(1);
+        }
+        (Screen::TodoScreen, KeyCode::Char('K'), false) => {
+            futs.push(Box::pin(app.todo_state.move_selected(&mut app.db, -1)));
+        }
+        (Screen::TodoScreen, KeyCode::Char('J'), false) => {
+            futs.push(Box::pin(app.todo_state.move_selected(&mut app.db, 1)));
+        }
+        (Screen::TodoScreen, KeyCode::Char('i'), false) => {
+            app.todo_state.toggle_form();
+        }
+        (Screen::TodoScreen, KeyCode::Char('e'), false) => {
+            app.todo_state.open_edit_form();
+        }
+        (Screen::TodoScreen, KeyCode::Enter, false) => {
+            futs.push(Box::pin(app.todo_state.toggle_completed(&mut app.db)));
+        }
+        (Screen::TodoScreen, KeyCode::Char('x'), false) => {
+            futs.push(Box::pin(app.todo_state.delete_selected(&mut app.db)));
+        }
+        (Screen::TodoScreen, KeyCode::Esc, true) => {
+            app.todo_state.toggle_form();
+        }
+        (Screen::TodoScreen, KeyCode::Char(_) | KeyCode::Backspace, true) => {
+            app.todo_state.form_state.send_input(&key.code);
+        }
+        (Screen::TodoScreen, KeyCode::Enter, true) => {
+            futs.push(Box::pin(app.todo_state.submit_form(&mut app.db)));
+        }
+
+        // Rules Screen -------------------------------------------------------
+        (Screen::RulesScreen, KeyCode::Up, false) => app.rules_state.increment_selected(-1),
+        (Screen::RulesScreen, KeyCode::Up, true) => {
+            app.rules_state.form_state.increment_selected(-1);
+        }
+        (Screen::RulesScreen, KeyCode::Down, false) => app.rules_state.increment_selected(1),
+        (Screen::RulesScreen, KeyCode::Down | KeyCode::Tab, true) => {
+            app.rules_state.form_state.increment_selected(1);
+        }
+        (Screen::RulesScreen, KeyCode::Char('K'), false) => {
+            futs.push(Box::pin(app.rules_state.move_selected(&mut app.db, -1)));
+        }
+        (Screen::RulesScreen, KeyCode::Char('J'), false) => {
+            futs.push(Box::pin(app.rules_state.move_selected(&mut app.db, 1)));
+        }
+        (Screen::RulesScreen, KeyCode::Char('i'), false) => app.rules_state.toggle_form(),
+        (Screen::RulesScreen, KeyCode::Char('e'), false) => app.rules_state.open_edit_form(),
+        (Screen::RulesScreen, KeyCode::Char('x'), false) => {
+            futs.push(Box::pin(app.rules_state.delete_selected(&mut app.db)));
+        }
+        (Screen::RulesScreen, KeyCode::Char('a'), false) => {
+            let (rules_state, money_state, db) =
+                (&mut app.rules_state, &mut app.money_state, &mut app.db);
+            futs.push(Box::pin(async move {
+                rules_state.reapply(db).await?;
+                money_state.reload_page(db).await
+            }));
+        }
+        (Screen::RulesScreen, KeyCode::Esc, false) => app.cur_screen = Screen::MoneyScreen,
+        (Screen::RulesScreen, KeyCode::Esc, true) => app.rules_state.toggle_form(),
+        (Screen::RulesScreen, KeyCode::Left | KeyCode::Right, true) => {
+            app.rules_state.form_state.send_input(&key.code);
+        }
+        (Screen::RulesScreen, KeyCode::Char(_) | KeyCode::Backspace, true) => {
+            app.rules_state.form_state.send_input(&key.code);
+        }
+        (Screen::RulesScreen, KeyCode::Enter, true) => {
+            futs.push(Box::pin(app.rules_state.submit_form(&mut app.db)));
+        }
+
+        // Exchange Rates Screen ----------------------------------------------
+        (Screen::RatesScreen, KeyCode::Up, false) => app.rates_state.increment_selected(-1),
+        (Screen::RatesScreen, KeyCode::Up, true) => {
+            app.rates_state.get_active_form_mut().increment_selected(-1);
+        }
+        (Screen::RatesScreen, KeyCode::Down, false) => app.rates_state.increment_selected(1),
+        (Screen::RatesScreen, KeyCode::Down | KeyCode::Tab, true) => {
+            app.rates_state.get_active_form_mut().increment_selected(1);
+        }
+        (Screen::RatesScreen, KeyCode::Char('i'), false) => app.rates_state.toggle_form(),
+        (Screen::RatesScreen, KeyCode::Char('I'), false) => app.rates_state.toggle_import_form(),
+        (Screen::RatesScreen, KeyCode::Char('x'), false) => {
+            futs.push(Box::pin(app.rates_state.delete_selected(&mut app.db)));
+        }
+        (Screen::RatesScreen, KeyCode::Esc, false) => {
+            // totals and the currency picker depend on the rates
+            app.cur_screen = Screen::MoneyScreen;
+            futs.push(Box::pin(app.money_state.refresh_currencies(&mut app.db)));
+        }
+        (Screen::RatesScreen, KeyCode::Esc, true) if app.rates_state.show_import_form => {
+            app.rates_state.toggle_import_form();
+        }
+        (Screen::RatesScreen, KeyCode::Esc, true) => app.rates_state.toggle_form(),
+        (
+            Screen::RatesScreen,
+            KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Left | KeyCode::Right,
+            true,
+        ) => {
+            app.rates_state.get_active_form_mut().send_input(&key.code);
+        }
+        (Screen::RatesScreen, KeyCode::Enter, true) if app.rates_state.show_import_form => {
+            futs.push(Box::pin(app.rates_state.submit_import_form(&mut app.db)));
+        }
+        (Screen::RatesScreen, KeyCode::Enter, true) => {
+            futs.push(Box::pin(app.rates_state.submit_form(&mut app.db)));
         }
 
         // All Screens --------------------------------------------------------
@@ -245,6 +684,7 @@ fn read_input<'a>(
         (_, KeyCode::Char('g'), false) => app.cur_screen = Screen::GradeScreen,
         (_, KeyCode::Char('m'), false) => app.cur_screen = Screen::MoneyScreen,
         (_, KeyCode::Char('f'), false) => app.cur_screen = Screen::FilmScreen,
+        (_, KeyCode::Char('t'), false) => app.cur_screen = Screen::TodoScreen,
         (_, KeyCode::Char('q'), false) => return true,
 
         _ => {}
@@ -0,0 +1,4 @@
+ALTER TABLE todos ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE todos ADD COLUMN completed_at TEXT;
+ALTER TABLE todos ADD COLUMN created_at TEXT NOT NULL DEFAULT '1970-01-01T00:00:00+00:00';
+UPDATE todos SET position = id, created_at = strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now');
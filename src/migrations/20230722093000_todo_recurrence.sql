@@ -0,0 +1,2 @@
+ALTER TABLE todos ADD COLUMN recurrence TEXT;
+ALTER TABLE todos ADD COLUMN due_date TEXT;
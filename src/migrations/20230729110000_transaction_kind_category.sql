@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS categories (
+    id INTEGER PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL UNIQUE
+);
+INSERT INTO categories (name) VALUES
+    ('Bills'),
+    ('Eating Out'),
+    ('Groceries'),
+    ('Salary'),
+    ('Shopping'),
+    ('Transport');
+ALTER TABLE transactions ADD COLUMN kind TEXT NOT NULL DEFAULT 'expense';
+ALTER TABLE transactions ADD COLUMN category_id INTEGER REFERENCES categories(id);
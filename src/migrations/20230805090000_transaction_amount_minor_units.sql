@@ -0,0 +1,15 @@
+-- amounts were pounds in a REAL column, store them as whole pence instead
+CREATE TABLE transactions_new (
+    id INTEGER PRIMARY KEY NOT NULL,
+    title TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    details TEXT,
+    date TEXT NOT NULL,
+    kind TEXT NOT NULL DEFAULT 'expense',
+    category_id INTEGER REFERENCES categories(id)
+);
+INSERT INTO transactions_new (id, title, amount, details, date, kind, category_id)
+SELECT id, title, CAST(ROUND(amount * 100) AS INTEGER), details, date, kind, category_id
+FROM transactions;
+DROP TABLE transactions;
+ALTER TABLE transactions_new RENAME TO transactions;
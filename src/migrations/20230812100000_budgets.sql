@@ -0,0 +1,8 @@
+-- a budget applies from its month until a later one for the same category
+-- replaces it, a NULL category covers all spending
+CREATE TABLE IF NOT EXISTS budgets (
+    id INTEGER PRIMARY KEY NOT NULL,
+    category_id INTEGER REFERENCES categories(id),
+    month TEXT NOT NULL,
+    amount INTEGER NOT NULL
+);
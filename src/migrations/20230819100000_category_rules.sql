@@ -0,0 +1,10 @@
+-- rules are tried in position order, the first match sets the category
+CREATE TABLE IF NOT EXISTS category_rules (
+    id INTEGER PRIMARY KEY NOT NULL,
+    kind TEXT NOT NULL DEFAULT 'contains',
+    pattern TEXT NOT NULL,
+    min_amount INTEGER,
+    max_amount INTEGER,
+    category_id INTEGER NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL
+);
@@ -0,0 +1,12 @@
+-- occurrences up to today are copied into transactions when the app starts,
+-- next_date is the first one that hasn't been yet
+CREATE TABLE IF NOT EXISTS recurring_transactions (
+    id INTEGER PRIMARY KEY NOT NULL,
+    title TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    details TEXT NOT NULL DEFAULT '',
+    kind TEXT NOT NULL DEFAULT 'expense',
+    category_id INTEGER REFERENCES categories(id),
+    recurrence TEXT NOT NULL,
+    next_date TEXT NOT NULL
+);
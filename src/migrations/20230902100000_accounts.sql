@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS accounts (
+    id INTEGER PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL UNIQUE,
+    opening_balance INTEGER NOT NULL DEFAULT 0
+);
+INSERT INTO accounts (id, name) VALUES (1, 'Current');
+
+-- existing transactions go in the first account, sqlite doesn't allow a
+-- foreign key on an added column with a default
+ALTER TABLE transactions ADD COLUMN account_id INTEGER NOT NULL DEFAULT 1;
+-- the other half of a transfer between accounts, deleting either removes both
+ALTER TABLE transactions ADD COLUMN transfer_id INTEGER REFERENCES transactions(id) ON DELETE CASCADE;
+ALTER TABLE recurring_transactions ADD COLUMN account_id INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,11 @@
+-- code of the currency the amount is in, NULL for the base currency
+ALTER TABLE transactions ADD COLUMN currency TEXT;
+
+-- value of one unit of `currency` in the base currency, used for transactions
+-- from `date` until the currency's next rate
+CREATE TABLE IF NOT EXISTS exchange_rates (
+    currency TEXT NOT NULL,
+    date TEXT NOT NULL,
+    rate REAL NOT NULL,
+    PRIMARY KEY (currency, date)
+);
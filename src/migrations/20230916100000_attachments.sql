@@ -0,0 +1,7 @@
+-- files such as receipt scans kept alongside a transaction
+CREATE TABLE IF NOT EXISTS attachments (
+    id INTEGER PRIMARY KEY NOT NULL,
+    transaction_id INTEGER NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    path TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS attachments_transaction_id ON attachments (transaction_id);
@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS modules (
+    id INTEGER PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    -- year of study, e.g. 1 for first year
+    year INTEGER NOT NULL DEFAULT 1,
+    position INTEGER NOT NULL
+);
+
+-- weight is the percentage of the module mark the assessment counts for
+CREATE TABLE IF NOT EXISTS assessments (
+    id INTEGER PRIMARY KEY NOT NULL,
+    module_id INTEGER NOT NULL REFERENCES modules(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    percentage REAL NOT NULL,
+    weight REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS assessments_module_id ON assessments (module_id);
@@ -0,0 +1,3 @@
+-- optional term within the module's year of study
+ALTER TABLE modules ADD COLUMN term TEXT;
+ALTER TABLE modules ADD COLUMN credits INTEGER NOT NULL DEFAULT 15;
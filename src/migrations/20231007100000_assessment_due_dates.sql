@@ -0,0 +1,15 @@
+-- marks become optional, work not marked yet was stored with a mark of 0
+-- before so those become NULL
+CREATE TABLE assessments_new (
+    id INTEGER PRIMARY KEY NOT NULL,
+    module_id INTEGER NOT NULL REFERENCES modules(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    percentage REAL,
+    weight REAL NOT NULL,
+    due_date TEXT
+);
+INSERT INTO assessments_new (id, module_id, name, percentage, weight)
+SELECT id, module_id, name, NULLIF(percentage, 0), weight FROM assessments;
+DROP TABLE assessments;
+ALTER TABLE assessments_new RENAME TO assessments;
+CREATE INDEX IF NOT EXISTS assessments_module_id ON assessments (module_id);
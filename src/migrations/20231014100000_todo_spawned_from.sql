@@ -0,0 +1,2 @@
+-- the todo whose completion added this one as its next occurrence
+ALTER TABLE todos ADD COLUMN spawned_from INTEGER REFERENCES todos(id) ON DELETE SET NULL;
@@ -0,0 +1,246 @@
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, AddAssign, Neg, Sub},
+};
+
+use sqlx::{
+    encode::IsNull,
+    error::BoxDynError,
+    sqlite::{SqliteArgumentValue, SqliteTypeInfo, SqliteValueRef},
+    Decode, Encode, Sqlite, Type,
+};
+
+use crate::config::CONFIG;
+
+/// Decimal places in a major unit, amounts are stored as hundredths
+const DECIMALS: u32 = 2;
+
+/// An amount of money as a whole number of minor units (e.g. pence) so sums
+/// never drift
+///
+/// The symbol shown comes from the currency config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+    pub fn from_minor(minor: i64) -> Money {
+        Money(minor)
+    }
+
+    pub fn from_major(major: i64) -> Money {
+        Money(major * 10_i64.pow(DECIMALS))
+    }
+
+    pub fn minor(self) -> i64 {
+        self.0
+    }
+
+    pub fn abs(self) -> Money {
+        Money(self.0.abs())
+    }
+
+    /// Whole major units, rounded towards zero
+    pub fn major(self) -> i64 {
+        self.0 / 10_i64.pow(DECIMALS)
+    }
+
+    /// Parse a decimal amount such as `12.5`
+    pub fn parse(text: &str) -> Option<Money> {
+        Money::parse_with_decimals(text, DECIMALS)
+    }
+
+    /// Parse a decimal amount, rounding any decimal places past the stored ones
+    /// half away from zero, e.g. `-12.345` becomes `-12.35`
+    pub fn parse_rounded(text: &str) -> Option<Money> {
+        let text = text.trim();
+        let (kept, extra) = match text.split_once('.') {
+            Some((major, minor))
+                if minor.len() > DECIMALS as usize && minor.chars().all(|c| c.is_ascii_digit()) =>
+            {
+                let (minor, extra) = minor.split_at(DECIMALS as usize);
+                (format!("{}.{}", major, minor), extra)
+            }
+            _ => (text.to_owned(), ""),
+        };
+        let money = Money::parse(&kept)?;
+        Some(match extra.bytes().next() {
+            Some(b'5'..=b'9') if text.starts_with('-') => money - Money(1),
+            Some(b'5'..=b'9') => money + Money(1),
+            _ => money,
+        })
+    }
+
+    /// Parse a decimal amount, failing if it has more than `decimals` decimal
+    /// places
+    pub fn parse_with_decimals(text: &str, decimals: u32) -> Option<Money> {
+        let text = text.trim();
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let (major, minor) = text.split_once('.').unwrap_or((text, ""));
+        if (major.is_empty() && minor.is_empty())
+            || minor.len() > decimals as usize
+            || !major
+                .chars()
+                .chain(minor.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return None;
+        }
+        let scale = 10_i64.pow(decimals);
+        let major = if major.is_empty() {
+            0
+        } else {
+            major.parse::<i64>().ok()?
+        };
+        let minor = if minor.is_empty() {
+            0
+        } else {
+            minor.parse::<i64>().ok()? * 10_i64.pow(decimals - minor.len() as u32)
+        };
+        let value = major.checked_mul(scale)?.checked_add(minor)?;
+        Some(Money(if negative { -value } else { value }))
+    }
+
+    /// The amount without a currency symbol, e.g. `-4.50`
+    pub fn to_decimal_string(self) -> String {
+        self.format_with("", DECIMALS, false)
+    }
+
+    /// The amount with the configured currency symbol, e.g. `-£4.50`,
+    /// optionally with `+` when positive
+    pub fn format(self, show_plus: bool) -> String {
+        self.format_with(&CONFIG.currency.symbol, DECIMALS, show_plus)
+    }
+
+    /// The amount with a currency code instead of the symbol, e.g. `-USD 4.50`
+    pub fn format_code(self, code: &str, show_plus: bool) -> String {
+        self.format_with(&format!("{} ", code), DECIMALS, show_plus)
+    }
+
+    pub fn format_with(self, symbol: &str, decimals: u32, show_plus: bool) -> String {
+        let sign = if self.0 < 0 {
+            "-"
+        } else if show_plus {
+            "+"
+        } else {
+            ""
+        };
+        let scale = 10_u64.pow(decimals);
+        let value = self.0.unsigned_abs();
+        if decimals == 0 {
+            format!("{}{}{}", sign, symbol, value)
+        } else {
+            format!(
+                "{}{}{}.{:0>4$}",
+                sign,
+                symbol,
+                value / scale,
+                value % scale,
+                decimals as usize
+            )
+        }
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(false))
+    }
+}
+
+impl Type<Sqlite> for Money {
+    fn type_info() -> SqliteTypeInfo {
+        <i64 as Type<Sqlite>>::type_info()
+    }
+
+    fn compatible(ty: &SqliteTypeInfo) -> bool {
+        <i64 as Type<Sqlite>>::compatible(ty)
+    }
+}
+
+impl<'q> Encode<'q, Sqlite> for Money {
+    fn encode_by_ref(&self, buf: &mut Vec<SqliteArgumentValue<'q>>) -> IsNull {
+        Encode::<Sqlite>::encode(self.minor(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Sqlite> for Money {
+    fn decode(value: SqliteValueRef<'r>) -> Result<Self, BoxDynError> {
+        Ok(Money::from_minor(<i64 as Decode<Sqlite>>::decode(value)?))
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money(self.0 - other.0)
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::default(), Add::add)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Money::parse_with_decimals("12.5", 2), Some(Money(1250)));
+        assert_eq!(Money::parse_with_decimals("0.07", 2), Some(Money(7)));
+        assert_eq!(Money::parse_with_decimals(".5", 2), Some(Money(50)));
+        assert_eq!(Money::parse_with_decimals("3.", 2), Some(Money(300)));
+        assert_eq!(Money::parse_with_decimals("-1.01", 2), Some(Money(-101)));
+        assert_eq!(Money::parse_with_decimals("120", 0), Some(Money(120)));
+        assert_eq!(Money::parse_with_decimals("1.005", 2), None);
+        assert_eq!(Money::parse_with_decimals("1.5", 0), None);
+        assert_eq!(Money::parse_with_decimals("1e3", 2), None);
+        assert_eq!(Money::parse_with_decimals(".", 2), None);
+
+        assert_eq!(Money::parse_rounded("-12.345"), Some(Money(-1235)));
+        assert_eq!(Money::parse_rounded("12.3449"), Some(Money(1234)));
+        assert_eq!(Money::parse_rounded("-0.005"), Some(Money(-1)));
+        assert_eq!(Money::parse_rounded("0.5"), Some(Money(50)));
+        assert_eq!(Money::parse_rounded("1.23x"), None);
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(Money(1250).format_with("£", 2, false), "£12.50");
+        assert_eq!(Money(7).format_with("£", 2, true), "+£0.07");
+        assert_eq!(Money(-101).format_with("$", 2, true), "-$1.01");
+        assert_eq!(Money(120).format_with("¥", 0, false), "¥120");
+        // summing minor units doesn't drift like summing floats
+        let total: Money = std::iter::repeat_n(Money(10), 1000).sum();
+        assert_eq!(total.format_with("£", 2, false), "£100.00");
+    }
+}
@@ -1,21 +1,40 @@
-use chrono::{DateTime, Datelike, Utc};
+use std::collections::HashMap;
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
+use crossterm::event::KeyCode;
 use log::info;
 use tui::{
     buffer::Buffer,
-    layout::Rect,
-    style::Modifier,
-    widgets::{BorderType, Borders, StatefulWidget, Widget},
+    layout::{Alignment, Rect},
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{BarChart, Block, BorderType, Borders, Paragraph, StatefulWidget, Widget, Wrap},
 };
 
 use crate::{
     button::Button,
-    db::{MoneyTransaction, DB},
+    category_rules::RuleSet,
+    config::CONFIG,
+    db::{
+        Account, Attachment, Budget, Category, DbError, MoneyTransaction, RecurringTransaction,
+        StatsPeriod, TransactionCursor, TransactionFilter, TransactionKind, TransactionSort, DB,
+        DEFAULT_ACCOUNT_ID,
+    },
+    exporter,
     form::{
-        DateField, FloatField, Form, FormField, FormFieldStyle, FormState, FormValue, IntegerField,
-        TextField,
+        render_form_popup, ChoiceField, DateField, Form, FormField, FormFieldStyle, FormState,
+        FormValue, IntegerField, MoneyField, TextField,
     },
+    fuzzy_search::{FuzzyMatch, FuzzySearch},
+    importer,
+    money::Money,
+    popup::Popup,
+    progress_bar::ProgressBar,
+    recurrence::Recurrence,
     styles::AppStyles,
-    util::{draw_rect_borders, generic_increment},
+    util::{
+        centered_rect, clear_area, draw_rect_borders, expand_home, generic_increment, open_file,
+    },
 };
 
 pub struct MoneyTracker;
@@ -24,17 +43,137 @@ pub struct MoneyTracker;
 pub struct MoneyTrackerState {
     pub search_form: FormState,
     pub add_form: FormState,
+    pub budget_form: FormState,
+    pub import_form: FormState,
+    pub export_form: FormState,
+    pub recurring_form: FormState,
+    pub jump_form: FormState,
+    pub account_form: FormState,
+    pub transfer_form: FormState,
+    pub attachment_form: FormState,
     pub transactions: Vec<MoneyTransaction>,
     pub search_form_selected: bool,
     pub add_form_selected: bool,
+    pub show_delete_popup: bool,
+    pub show_budget_form: bool,
+    pub show_import_form: bool,
+    /// Why the last file couldn't be imported, shown in the import form
+    import_error: Option<String>,
+    pub import_preview: Option<ImportPreview>,
+    pub show_export_form: bool,
+    /// Outcome of the last export, shown in the export form
+    export_message: Option<Result<String, String>>,
+    pub show_jump_form: bool,
+    pub show_account_form: bool,
+    pub show_transfer_form: bool,
+    /// Incremental search over the transactions matching the filters, shown
+    /// in place of the list while it is open
+    pub fuzzy_search: Option<FuzzySearch>,
+    fuzzy_selected: usize,
+    /// Details of the selected transaction and its attachments
+    pub show_detail_popup: bool,
+    pub show_attachment_form: bool,
+    attachments: Vec<Attachment>,
+    selected_attachment: usize,
+    /// Why an attachment couldn't be added or opened
+    attachment_error: Option<String>,
+    /// Why the last transfer couldn't be made, shown in the transfer form
+    transfer_error: Option<String>,
+    accounts: Vec<Account>,
+    /// Index of the account the transactions are limited to, `None` for all
+    selected_account: Option<usize>,
+    /// Why the date in the jump form couldn't be used
+    jump_error: Option<String>,
+    /// Why the month in the budget form couldn't be used
+    budget_error: Option<String>,
+    /// Show spending statistics in place of the transaction list
+    pub show_stats: bool,
+    /// Show recurring transactions in place of the transaction list
+    pub show_subscriptions: bool,
+    pub show_recurring_form: bool,
+    /// Why the last recurring transaction couldn't be added, shown in its form
+    recurring_error: Option<String>,
+    recurring: Vec<RecurringTransaction>,
+    selected_recurring: u32,
     pub selected_transaction: u32,
-    current_page: u32,
-    num_of_pages: u32,
-    max_transactions: Option<u32>,
-    page_offsets: Vec<u32>,
-    filters: (String, f32, f32),
+    editing: Option<i64>,
+    /// Where the current page starts, `None` for the first page
+    page_start: Option<TransactionCursor>,
+    /// Number of filtered transactions before the current page
+    num_before_page: u32,
+    num_of_transactions: u32,
+    /// Lines the transaction list has to fill, known once it has been drawn
+    list_height: Option<u32>,
+    filters: TransactionFilter,
+    categories: Vec<Category>,
+    /// The base currency followed by those with exchange rates
+    currencies: Vec<String>,
+    /// Net total and end of day balance for each day with transactions
+    day_balances: HashMap<NaiveDate, (Money, Money)>,
+    /// Budgets for the current month with how much has been spent
+    budgets: Vec<(Budget, Money)>,
+    stats_period: StatsPeriod,
+    stats: Option<SpendingStats>,
+}
+
+/// Transactions read from a statement waiting to be accepted
+#[derive(Debug)]
+pub struct ImportPreview {
+    /// Each transaction with whether it already exists and whether it will be
+    /// added
+    rows: Vec<(MoneyTransaction, bool, bool)>,
+    selected: usize,
+}
+
+impl ImportPreview {
+    pub fn increment_selected(&mut self, amount: i32) {
+        let mut selected = self.selected as u32;
+        generic_increment(&mut selected, 0, self.rows.len() as u32 - 1, amount);
+        self.selected = selected as usize;
+    }
+
+    pub fn toggle_selected(&mut self) {
+        let (_, _, accepted) = &mut self.rows[self.selected];
+        *accepted = !*accepted;
+    }
 }
 
+/// Summary of recent spending for the statistics view
+#[derive(Debug)]
+struct SpendingStats {
+    /// Spending in each of the last `STATS_PERIODS` periods, oldest first
+    period_totals: Vec<(NaiveDate, Money)>,
+    /// Categories with the most spending this month
+    top_categories: Vec<(String, Money)>,
+    /// Average spending per day over the last 30 days
+    daily_average: Money,
+    this_month: Money,
+    /// Spending last month up to the same day of the month as today
+    last_month_to_date: Money,
+    last_month: Money,
+}
+
+/// Number of bars in the statistics chart
+const STATS_PERIODS: usize = 12;
+/// Transactions loaded for each page, more than can fit on the screen so a
+/// page doesn't need reloading when the terminal is resized
+const PAGE_FETCH_LIMIT: u32 = 200;
+/// How many days ahead upcoming recurring charges are listed for
+const UPCOMING_DAYS: i64 = 30;
+
+/// Positions of the currency and category fields in the add form
+const CURRENCY_FIELD: usize = 2;
+const CATEGORY_FIELD: usize = 4;
+/// Position of the category field in the recurring transaction form
+const RECURRING_CATEGORY_FIELD: usize = 3;
+/// Positions of the from and to account fields in the transfer form
+const TRANSFER_FROM_FIELD: usize = 0;
+const TRANSFER_TO_FIELD: usize = 1;
+/// Position of the category field in the budget form
+const BUDGET_CATEGORY_FIELD: usize = 0;
+/// Category option in the budget form for a budget covering all spending
+const OVERALL_BUDGET: &str = "Overall";
+
 impl MoneyTracker {
     pub fn new() -> MoneyTracker {
         MoneyTracker {}
@@ -42,24 +181,28 @@ impl MoneyTracker {
 }
 
 impl MoneyTrackerState {
-    pub fn new() -> MoneyTrackerState {
+    pub async fn new(db: &mut DB) -> Result<MoneyTrackerState, DbError> {
         let mut search_form = FormState::new();
         search_form.add_field(Box::new(TextField::new(
             "".to_owned(),
             false,
             FormFieldStyle::new("Keyword".to_owned()),
         )));
-        search_form.add_field(Box::new(FloatField::new(
-            0.,
-            0.,
-            100000.,
+        search_form.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            false,
+            FormFieldStyle::new("Details".to_owned()),
+        )));
+        let max_amount = Money::from_major(100000);
+        search_form.add_field(Box::new(MoneyField::new(
+            Money::default(),
+            max_amount,
             false,
             FormFieldStyle::new("Min".to_owned()),
         )));
-        search_form.add_field(Box::new(FloatField::new(
-            100000.,
-            0.,
-            100000.,
+        search_form.add_field(Box::new(MoneyField::new(
+            max_amount,
+            max_amount,
             false,
             FormFieldStyle::new("Max".to_owned()),
         )));
@@ -70,13 +213,26 @@ impl MoneyTrackerState {
             true,
             FormFieldStyle::new("Title".to_owned()),
         )));
-        add_form.add_field(Box::new(FloatField::new(
-            0.,
-            0.,
-            100000.,
+        add_form.add_field(Box::new(MoneyField::new(
+            Money::default(),
+            max_amount,
             true,
             FormFieldStyle::new("Amount".to_owned()),
         )));
+        let currencies = MoneyTrackerState::get_currencies(db).await?;
+        add_form.add_field(Box::new(MoneyTrackerState::currency_field(&currencies)));
+        add_form.add_field(Box::new(ChoiceField::new(
+            [TransactionKind::Expense, TransactionKind::Income]
+                .iter()
+                .map(|k| k.get_name().to_owned())
+                .collect(),
+            TransactionKind::Expense.get_name().to_owned(),
+            false,
+            true,
+            FormFieldStyle::new("Type (←/→)".to_owned()),
+        )));
+        let categories = db.get_categories().await?;
+        add_form.add_field(Box::new(MoneyTrackerState::category_field(&categories)));
         add_form.add_field(Box::new(TextField::new(
             "".to_owned(),
             false,
@@ -87,114 +243,603 @@ impl MoneyTrackerState {
             false,
             FormFieldStyle::new("Date".to_owned()),
         )));
-        MoneyTrackerState {
+
+        let mut recurring_form = FormState::new();
+        recurring_form.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            true,
+            FormFieldStyle::new("Title".to_owned()),
+        )));
+        recurring_form.add_field(Box::new(MoneyField::new(
+            Money::default(),
+            max_amount,
+            true,
+            FormFieldStyle::new("Amount".to_owned()),
+        )));
+        recurring_form.add_field(Box::new(ChoiceField::new(
+            [TransactionKind::Expense, TransactionKind::Income]
+                .iter()
+                .map(|k| k.get_name().to_owned())
+                .collect(),
+            TransactionKind::Expense.get_name().to_owned(),
+            false,
+            true,
+            FormFieldStyle::new("Type (←/→)".to_owned()),
+        )));
+        recurring_form.add_field(Box::new(MoneyTrackerState::category_field(&categories)));
+        recurring_form.add_field(Box::new(TextField::new(
+            "monthly 1".to_owned(),
+            true,
+            FormFieldStyle::new("Repeat (daily/weekly mon,fri/monthly 15/every 3)".to_owned()),
+        )));
+        recurring_form.add_field(Box::new(DateField::new(
+            Utc::now(),
+            true,
+            FormFieldStyle::new("Next date".to_owned()),
+        )));
+
+        let mut jump_form = FormState::new();
+        jump_form.add_field(Box::new(TextField::new(
+            Local::now().format("%Y-%m-%d").to_string(),
+            true,
+            FormFieldStyle::new("Date (YYYY-MM-DD)".to_owned()),
+        )));
+
+        let mut account_form = FormState::new();
+        account_form.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            true,
+            FormFieldStyle::new("Name (existing name to edit)".to_owned()),
+        )));
+        account_form.add_field(Box::new(MoneyField::new(
+            Money::default(),
+            max_amount,
+            false,
+            FormFieldStyle::new("Opening balance".to_owned()),
+        )));
+
+        let accounts = db.get_accounts().await?;
+        let mut transfer_form = FormState::new();
+        transfer_form.add_field(Box::new(MoneyTrackerState::account_field(
+            &accounts, 0, "From",
+        )));
+        transfer_form.add_field(Box::new(MoneyTrackerState::account_field(
+            &accounts, 1, "To",
+        )));
+        transfer_form.add_field(Box::new(MoneyField::new(
+            Money::default(),
+            max_amount,
+            true,
+            FormFieldStyle::new("Amount".to_owned()),
+        )));
+        transfer_form.add_field(Box::new(DateField::new(
+            Utc::now(),
+            true,
+            FormFieldStyle::new("Date".to_owned()),
+        )));
+        transfer_form.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            false,
+            FormFieldStyle::new("Details".to_owned()),
+        )));
+
+        let mut attachment_form = FormState::new();
+        attachment_form.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            true,
+            FormFieldStyle::new("File path".to_owned()),
+        )));
+
+        let mut budget_form = FormState::new();
+        budget_form.add_field(Box::new(MoneyTrackerState::budget_category_field(
+            &categories,
+        )));
+        budget_form.add_field(Box::new(TextField::new(
+            Local::now().format("%Y-%m").to_string(),
+            true,
+            FormFieldStyle::new("From month (YYYY-MM)".to_owned()),
+        )));
+        budget_form.add_field(Box::new(MoneyField::new(
+            Money::default(),
+            max_amount,
+            true,
+            FormFieldStyle::new("Limit (0 removes)".to_owned()),
+        )));
+
+        let mut import_form = FormState::new();
+        import_form.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            true,
+            FormFieldStyle::new("Statement file (.csv/.ofx/.qfx)".to_owned()),
+        )));
+
+        let mut export_form = FormState::new();
+        export_form.add_field(Box::new(TextField::new(
+            "~/transactions.csv".to_owned(),
+            true,
+            FormFieldStyle::new("File (.csv/.json)".to_owned()),
+        )));
+        export_form.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            false,
+            FormFieldStyle::new("From (YYYY-MM-DD)".to_owned()),
+        )));
+        export_form.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            false,
+            FormFieldStyle::new("To (YYYY-MM-DD)".to_owned()),
+        )));
+
+        let mut state = MoneyTrackerState {
             search_form,
             add_form,
+            budget_form,
+            import_form,
+            export_form,
+            recurring_form,
+            jump_form,
+            account_form,
+            transfer_form,
+            attachment_form,
             transactions: vec![],
             search_form_selected: false,
             add_form_selected: false,
+            show_delete_popup: false,
+            show_budget_form: false,
+            show_import_form: false,
+            import_error: None,
+            import_preview: None,
+            show_export_form: false,
+            export_message: None,
+            show_stats: false,
+            show_jump_form: false,
+            jump_error: None,
+            budget_error: None,
+            show_account_form: false,
+            show_transfer_form: false,
+            fuzzy_search: None,
+            fuzzy_selected: 0,
+            show_detail_popup: false,
+            show_attachment_form: false,
+            attachments: vec![],
+            selected_attachment: 0,
+            attachment_error: None,
+            transfer_error: None,
+            accounts,
+            selected_account: None,
+            show_subscriptions: false,
+            show_recurring_form: false,
+            recurring_error: None,
+            recurring: db.get_recurring_transactions().await?,
+            selected_recurring: 0,
             selected_transaction: 0,
-            current_page: 1,
-            num_of_pages: 1,
-            max_transactions: None,
-            page_offsets: vec![0],
-            filters: ("".to_owned(), 0., 100000.),
+            editing: None,
+            page_start: None,
+            num_before_page: 0,
+            num_of_transactions: 0,
+            list_height: None,
+            filters: TransactionFilter::new(),
+            categories,
+            currencies,
+            day_balances: HashMap::new(),
+            budgets: vec![],
+            stats_period: StatsPeriod::Day,
+            stats: None,
+        };
+        state.refresh_transactions(db).await?;
+        Ok(state)
+    }
+
+    /// Category picker listing the existing categories, a new one can be typed
+    /// in
+    fn category_field(categories: &[Category]) -> ChoiceField {
+        ChoiceField::new(
+            categories.iter().map(|c| c.name.clone()).collect(),
+            "".to_owned(),
+            true,
+            false,
+            FormFieldStyle::new("Category (←/→ or type new)".to_owned()),
+        )
+    }
+
+    /// The base currency followed by those with exchange rates
+    async fn get_currencies(db: &mut DB) -> Result<Vec<String>, DbError> {
+        let mut currencies = vec![CONFIG.currency.code.clone()];
+        for rate in db.get_exchange_rates().await? {
+            if !currencies.contains(&rate.currency) {
+                currencies.push(rate.currency);
+            }
+        }
+        Ok(currencies)
+    }
+
+    /// Whether the transaction is in a currency with no exchange rates, so it
+    /// is left out of totals and balances
+    fn is_missing_rate(&self, transaction: &MoneyTransaction) -> bool {
+        transaction
+            .currency
+            .as_ref()
+            .is_some_and(|c| !self.currencies.contains(c))
+    }
+
+    /// Currency picker starting on the base currency, other currencies need an
+    /// exchange rate before they can be picked
+    fn currency_field(currencies: &[String]) -> ChoiceField {
+        ChoiceField::new(
+            currencies.to_vec(),
+            CONFIG.currency.code.clone(),
+            false,
+            true,
+            FormFieldStyle::new("Currency (←/→)".to_owned()),
+        )
+    }
+
+    /// Pick up changed exchange rates in the currency picker and totals
+    pub async fn refresh_currencies(&mut self, db: &mut DB) -> Result<(), DbError> {
+        self.currencies = MoneyTrackerState::get_currencies(db).await?;
+        self.add_form.get_fields_mut()[CURRENCY_FIELD] =
+            Box::new(MoneyTrackerState::currency_field(&self.currencies));
+        self.reload_page(db).await
+    }
+
+    /// Account picker starting on the account at `default`
+    fn account_field(accounts: &[Account], default: usize, title: &str) -> ChoiceField {
+        ChoiceField::new(
+            accounts.iter().map(|a| a.name.clone()).collect(),
+            accounts
+                .get(default)
+                .or(accounts.first())
+                .map_or("".to_owned(), |a| a.name.clone()),
+            false,
+            true,
+            FormFieldStyle::new(format!("{} (←/→)", title)),
+        )
+    }
+
+    /// Category picker for budgets, limited to existing categories or overall
+    fn budget_category_field(categories: &[Category]) -> ChoiceField {
+        ChoiceField::new(
+            std::iter::once(OVERALL_BUDGET.to_owned())
+                .chain(categories.iter().map(|c| c.name.clone()))
+                .collect(),
+            OVERALL_BUDGET.to_owned(),
+            false,
+            true,
+            FormFieldStyle::new("Category (←/→)".to_owned()),
+        )
+    }
+
+    /// Reload the account and daily balances and this month's budgets
+    async fn refresh_totals(&mut self, db: &mut DB) -> Result<(), DbError> {
+        self.accounts = db.get_accounts().await?;
+        self.day_balances = db
+            .get_daily_balances(self.filters.account_id)
+            .await?
+            .into_iter()
+            .map(|(day, total, balance)| (day, (total, balance)))
+            .collect();
+
+        let month = Local::now().format("%Y-%m").to_string();
+        let spending = db.get_monthly_spending(&month).await?;
+        self.budgets = db
+            .get_budgets(&month)
+            .await?
+            .into_iter()
+            .map(|b| {
+                let spent = match b.category_id {
+                    Some(_) => spending.get(&b.category_id).copied().unwrap_or_default(),
+                    None => spending.values().copied().sum(),
+                };
+                (b, spent)
+            })
+            .collect();
+
+        if self.show_stats {
+            self.refresh_stats(db).await?;
+        }
+        Ok(())
+    }
+
+    async fn refresh_stats(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let today = Local::now().date_naive();
+        let tomorrow = today + Duration::days(1);
+
+        let period = self.stats_period;
+        let end = period.next_start(period.start_of(today));
+        let mut starts = vec![period.start_of(today)];
+        while starts.len() < STATS_PERIODS {
+            starts.push(period.prev_start(*starts.last().unwrap()));
+        }
+        starts.reverse();
+        let totals: HashMap<NaiveDate, Money> = db
+            .get_spending_totals(period, starts[0], end)
+            .await?
+            .into_iter()
+            .collect();
+        let period_totals = starts
+            .into_iter()
+            .map(|start| (start, totals.get(&start).copied().unwrap_or_default()))
+            .collect();
+
+        let month_start = StatsPeriod::Month.start_of(today);
+        let last_month_start = StatsPeriod::Month.prev_start(month_start);
+        let last_month_to_date_end =
+            (last_month_start + Duration::days(today.day() as i64)).min(month_start);
+        let last_30_days = db
+            .get_spending_between(today - Duration::days(29), tomorrow)
+            .await?;
+
+        self.stats = Some(SpendingStats {
+            period_totals,
+            top_categories: db.get_top_categories(month_start, tomorrow, 5).await?,
+            daily_average: Money::from_minor(last_30_days.minor() / 30),
+            this_month: db.get_spending_between(month_start, tomorrow).await?,
+            last_month_to_date: db
+                .get_spending_between(last_month_start, last_month_to_date_end)
+                .await?,
+            last_month: db
+                .get_spending_between(last_month_start, month_start)
+                .await?,
+        });
+        Ok(())
+    }
+
+    /// Switch between the transaction list and spending statistics
+    pub async fn toggle_stats(&mut self, db: &mut DB) -> Result<(), DbError> {
+        self.show_stats = !self.show_stats;
+        if self.show_stats {
+            self.show_subscriptions = false;
+            self.refresh_stats(db).await?;
+        }
+        Ok(())
+    }
+
+    /// Cycle whether the statistics chart shows days, weeks or months
+    pub async fn cycle_stats_period(&mut self, db: &mut DB) -> Result<(), DbError> {
+        self.stats_period = self.stats_period.next();
+        self.refresh_stats(db).await
+    }
+
+    /// Get the id of the named category, adding it to the picker if it is new
+    async fn resolve_category(&mut self, db: &mut DB, name: &str) -> Result<Option<i64>, DbError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Ok(None);
         }
+        if let Some(category) = self.categories.iter().find(|c| c.name == name) {
+            return Ok(Some(category.id));
+        }
+        let id = db.get_or_create_category(name).await?;
+        self.categories = db.get_categories().await?;
+        self.add_form.get_fields_mut()[CATEGORY_FIELD] =
+            Box::new(MoneyTrackerState::category_field(&self.categories));
+        self.recurring_form.get_fields_mut()[RECURRING_CATEGORY_FIELD] =
+            Box::new(MoneyTrackerState::category_field(&self.categories));
+        self.budget_form.get_fields_mut()[BUDGET_CATEGORY_FIELD] =
+            Box::new(MoneyTrackerState::budget_category_field(&self.categories));
+        Ok(Some(id))
     }
 
     pub fn increment_selected(&mut self, amount: i32) {
+        let num_visible = self.num_visible();
+        if num_visible == 0 {
+            return;
+        }
         generic_increment(
             &mut self.selected_transaction,
             0,
-            self.transactions.len() as u32 - 1,
+            num_visible as u32 - 1,
             amount,
         );
     }
 
-    pub async fn get_next_page(&mut self, db: &mut DB) {
-        if self.current_page < self.num_of_pages {
-            self.current_page += 1;
+    /// Number of transactions on the current page that fit in the list
+    fn num_visible(&self) -> usize {
+        match self.list_height {
+            Some(height) => num_that_fit(&self.transactions, height),
+            None => self.transactions.len(),
+        }
+    }
+
+    /// Load the transactions from the start of the current page
+    async fn load_page(&mut self, db: &mut DB) -> Result<(), DbError> {
+        self.transactions = db
+            .query_transactions(&self.filters, self.page_start.as_ref(), PAGE_FETCH_LIMIT)
+            .await?;
+        self.num_of_transactions = db.get_num_of_transactions(&self.filters).await?;
+        self.num_before_page = match &self.page_start {
+            Some(cursor) => {
+                db.get_num_of_transactions_before(&self.filters, cursor)
+                    .await?
+            }
+            None => 0,
+        };
+        self.selected_transaction = self
+            .selected_transaction
+            .min(self.num_visible().saturating_sub(1) as u32);
+        Ok(())
+    }
+
+    /// Start the page at the earliest of the transactions before `cursor` that
+    /// fit on one page
+    async fn load_page_ending_at(
+        &mut self,
+        db: &mut DB,
+        cursor: Option<&TransactionCursor>,
+    ) -> Result<(), DbError> {
+        let height = self.list_height.unwrap_or(PAGE_FETCH_LIMIT);
+        let before = db
+            .query_transactions_before(&self.filters, cursor, height)
+            .await?;
+        let num_fit = num_that_fit(&before, height);
+        if num_fit > 0 {
+            self.page_start = Some(TransactionCursor::at(&before[num_fit - 1]));
             self.selected_transaction = 0;
-            let max_trans = self.max_transactions.unwrap();
-            self.transactions = db
-                .query_transactions(
-                    &self.filters.0,
-                    self.filters.1,
-                    self.filters.2,
-                    max_trans,
-                    *self
-                        .page_offsets
-                        .get(self.current_page as usize - 1)
-                        .unwrap(),
-                )
-                .await;
-        }
-    }
-
-    pub async fn get_prev_page(&mut self, db: &mut DB) {
-        if self.current_page > 1 {
-            self.current_page -= 1;
+            self.load_page(db).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_next_page(&mut self, db: &mut DB) -> Result<(), DbError> {
+        if let Some(next) = self.transactions.get(self.num_visible()) {
+            self.page_start = Some(TransactionCursor::at(next));
             self.selected_transaction = 0;
-            let max_trans = self.max_transactions.unwrap();
-            self.transactions = db
-                .query_transactions(
-                    &self.filters.0,
-                    self.filters.1,
-                    self.filters.2,
-                    max_trans,
-                    *self
-                        .page_offsets
-                        .get(self.current_page as usize - 1)
-                        .unwrap(),
-                )
-                .await;
-        }
-    }
-
-    pub async fn submit_search_form(&mut self, db: &mut DB) {
+            self.load_page(db).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_prev_page(&mut self, db: &mut DB) -> Result<(), DbError> {
+        if let Some(start) = self.page_start {
+            self.load_page_ending_at(db, Some(&start)).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_first_page(&mut self, db: &mut DB) -> Result<(), DbError> {
+        self.page_start = None;
+        self.selected_transaction = 0;
+        self.load_page(db).await
+    }
+
+    pub async fn get_last_page(&mut self, db: &mut DB) -> Result<(), DbError> {
+        self.load_page_ending_at(db, None).await
+    }
+
+    pub fn toggle_jump_form(&mut self) {
+        self.show_jump_form = !self.show_jump_form;
+        self.jump_error = None;
+        if !self.show_jump_form {
+            self.jump_form.reset_fields();
+        }
+    }
+
+    /// Show the page starting at the date in the jump form, switching to
+    /// sorting by date if needed
+    pub async fn submit_jump_form(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let text = self.jump_form.get_fields()[0]
+            .get_internal_value()
+            .try_get_text_value()
+            .unwrap()
+            .trim()
+            .to_owned();
+        let date = match NaiveDate::parse_from_str(&text, "%Y-%m-%d") {
+            Ok(d) => d,
+            Err(_) => {
+                self.jump_error = Some(format!("Invalid date: {}", text));
+                return Ok(());
+            }
+        };
+        if !matches!(
+            self.filters.sort,
+            TransactionSort::DateDesc | TransactionSort::DateAsc
+        ) {
+            self.filters.sort = TransactionSort::DateDesc;
+        }
+        self.toggle_jump_form();
+        self.page_start = Some(TransactionCursor::on_date(date, self.filters.sort));
+        self.selected_transaction = 0;
+        self.load_page(db).await?;
+        // nothing on or after the date in this order, so show the end instead
+        if self.transactions.is_empty() {
+            self.get_last_page(db).await?;
+        }
+        self.select_transaction_list();
+        Ok(())
+    }
+
+    pub async fn submit_search_form(&mut self, db: &mut DB) -> Result<(), DbError> {
         let fields = self.search_form.get_fields();
         let vals: Vec<&FormValue> = fields.iter().map(|f| f.get_internal_value()).collect();
         match vals.as_slice() {
-            [k, min, max] => {
-                let keyword = k.try_get_text_value().unwrap();
-                let min = *min.try_get_float_value().unwrap();
-                let max = *max.try_get_float_value().unwrap();
-                self.filters = (keyword.clone(), min, max);
-                self.num_of_pages = (db
-                    .get_num_of_transaction_text_lines(&keyword, min, max)
-                    .await as f32
-                    / self.max_transactions.unwrap() as f32)
-                    .ceil() as u32;
-                self.transactions = db
-                    .query_transactions(&keyword, min, max, self.max_transactions.unwrap(), 0)
-                    .await;
+            [k, details, min, max] => {
+                self.filters.keyword = k.try_get_text_value().unwrap().clone();
+                self.filters.details = details.try_get_text_value().unwrap().clone();
+                self.filters.min = *min.try_get_money_value().unwrap();
+                self.filters.max = *max.try_get_money_value().unwrap();
                 self.search_form.reset_fields();
-                self.selected_transaction = 0;
-                self.current_page = 1;
-                self.page_offsets = vec![0];
-                self.select_transaction_list();
+                self.refresh_transactions(db).await?;
             }
             [..] => {}
         };
+        Ok(())
     }
 
-    pub async fn submit_add_form(&mut self, db: &mut DB) {
+    /// Cycle the order the transactions are listed in
+    pub async fn cycle_sort(&mut self, db: &mut DB) -> Result<(), DbError> {
+        self.filters.sort = self.filters.sort.next();
+        self.refresh_transactions(db).await
+    }
+
+    /// Re-run the current filters from the first page
+    async fn refresh_transactions(&mut self, db: &mut DB) -> Result<(), DbError> {
+        self.page_start = None;
+        self.selected_transaction = 0;
+        self.load_page(db).await?;
+        self.refresh_totals(db).await?;
+        self.select_transaction_list();
+        Ok(())
+    }
+
+    /// Re-run the current filters keeping the current page and selection where
+    /// possible
+    pub async fn reload_page(&mut self, db: &mut DB) -> Result<(), DbError> {
+        self.load_page(db).await?;
+        // everything from the start of the page on has gone
+        if self.transactions.is_empty() && self.page_start.is_some() {
+            self.get_last_page(db).await?;
+        }
+        self.refresh_totals(db).await
+    }
+
+    pub async fn submit_add_form(&mut self, db: &mut DB) -> Result<(), DbError> {
         let fields = self.add_form.get_fields();
-        let vals: Vec<&FormValue> = fields.iter().map(|f| f.get_internal_value()).collect();
+        let vals: Vec<FormValue> = fields
+            .iter()
+            .map(|f| f.get_internal_value().clone())
+            .collect();
         match vals.as_slice() {
-            [title, amount, details, date] => {
-                let new_trans = MoneyTransaction::new(
+            [title, amount, currency, kind, category, details, date] => {
+                let kind = TransactionKind::from_name(kind.try_get_text_value().unwrap()).unwrap();
+                let category_id = self
+                    .resolve_category(db, category.try_get_text_value().unwrap())
+                    .await?;
+                let mut new_trans = MoneyTransaction::new(
                     title.try_get_text_value().unwrap().clone(),
-                    *amount.try_get_float_value().unwrap(),
+                    *amount.try_get_money_value().unwrap(),
                     details.try_get_text_value().unwrap().clone(),
-                    date.try_get_date_value().unwrap().clone(),
+                    *date.try_get_date_value().unwrap(),
+                    kind,
+                    category_id,
                 );
-                db.add_transaction(&new_trans).await;
-                self.add_form.reset_fields();
-                self.submit_search_form(db).await;
+                // new transactions left without a category are given one by the
+                // rules, edits keep what the user picked
+                if self.editing.is_none() && new_trans.category_id.is_none() {
+                    let rules = RuleSet::new(db.get_category_rules().await?);
+                    new_trans.category_id = rules.category_for(&new_trans);
+                }
+                let currency = currency.try_get_text_value().unwrap();
+                if currency != &CONFIG.currency.code {
+                    new_trans.currency = Some(currency.clone());
+                }
+                match self.editing {
+                    Some(id) => {
+                        new_trans.id = id;
+                        db.update_transaction(&new_trans).await?;
+                        self.select_transaction_list();
+                        self.reload_page(db).await?;
+                    }
+                    None => {
+                        new_trans.account_id = self.target_account_id();
+                        db.add_transaction(&new_trans).await?;
+                        self.add_form.reset_fields();
+                        self.refresh_transactions(db).await?;
+                    }
+                }
             }
             [..] => {}
         };
+        Ok(())
     }
 
     pub fn select_search_form(&mut self) {
@@ -210,6 +855,571 @@ impl MoneyTrackerState {
     pub fn select_transaction_list(&mut self) {
         self.search_form_selected = false;
         self.add_form_selected = false;
+        // leaving the add form cancels an edit
+        if self.editing.take().is_some() {
+            self.add_form.reset_fields();
+        }
+    }
+
+    /// Load the selected transaction into the add form to be edited
+    pub fn edit_selected_transaction(&mut self) {
+        if self.transactions.is_empty() {
+            return;
+        }
+        let id = self.get_selected_transaction().id;
+        self.fill_add_form(false);
+        self.editing = Some(id);
+    }
+
+    /// Load a copy of the selected transaction dated now into the add form, to
+    /// be changed before it is added
+    pub fn duplicate_selected_transaction(&mut self) {
+        if self.transactions.is_empty() {
+            return;
+        }
+        self.select_transaction_list();
+        self.fill_add_form(true);
+    }
+
+    /// Fill the add form with the selected transaction and select it,
+    /// optionally with today's date instead of the transaction's
+    fn fill_add_form(&mut self, now: bool) {
+        let transaction = self.get_selected_transaction();
+        let values = [
+            FormValue::Text(transaction.title.clone()),
+            FormValue::Money(transaction.amount),
+            FormValue::Text(
+                transaction
+                    .currency
+                    .clone()
+                    .unwrap_or_else(|| CONFIG.currency.code.clone()),
+            ),
+            FormValue::Text(transaction.kind.get_name().to_owned()),
+            FormValue::Text(transaction.category.clone().unwrap_or_default()),
+            FormValue::Text(transaction.details.clone()),
+            FormValue::Date(if now { Utc::now() } else { transaction.date }),
+        ];
+        self.add_form.reset_fields();
+        for (field, value) in self.add_form.get_fields_mut().iter_mut().zip(values) {
+            field.set_value(value);
+        }
+        self.select_add_form();
+    }
+
+    /// Switch between the transaction list and recurring transactions
+    pub fn toggle_subscriptions(&mut self) {
+        self.show_subscriptions = !self.show_subscriptions;
+        if self.show_subscriptions {
+            self.show_stats = false;
+        }
+    }
+
+    pub fn increment_selected_recurring(&mut self, amount: i32) {
+        if self.recurring.is_empty() {
+            return;
+        }
+        generic_increment(
+            &mut self.selected_recurring,
+            0,
+            self.recurring.len() as u32 - 1,
+            amount,
+        );
+    }
+
+    /// Total of all recurring transactions averaged over a month
+    fn monthly_recurring_total(&self) -> Money {
+        self.recurring.iter().map(|r| r.monthly_amount()).sum()
+    }
+
+    /// Each occurrence of a recurring transaction up to `until`, soonest first
+    fn upcoming_recurring(&self, until: NaiveDate) -> Vec<(NaiveDate, &RecurringTransaction)> {
+        let mut upcoming = Vec::new();
+        for r in &self.recurring {
+            let mut date = r.next_date;
+            while date <= until {
+                upcoming.push((date, r));
+                date = r.recurrence.next_after(date);
+            }
+        }
+        upcoming.sort_by_key(|(date, _)| *date);
+        upcoming
+    }
+
+    pub fn toggle_recurring_form(&mut self) {
+        self.show_recurring_form = !self.show_recurring_form;
+        self.recurring_error = None;
+        if !self.show_recurring_form {
+            self.recurring_form.reset_fields();
+        }
+    }
+
+    /// Add a recurring transaction, any occurrences already due are added to
+    /// the transactions straight away
+    pub async fn submit_recurring_form(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let fields = self.recurring_form.get_fields();
+        let vals: Vec<FormValue> = fields
+            .iter()
+            .map(|f| f.get_internal_value().clone())
+            .collect();
+        if let [title, amount, kind, category, repeat, next_date] = vals.as_slice() {
+            let title = title.try_get_text_value().unwrap().trim().to_owned();
+            let amount = *amount.try_get_money_value().unwrap();
+            if title.is_empty() || amount.minor() == 0 {
+                self.recurring_error = Some("Enter a title and amount".to_owned());
+                return Ok(());
+            }
+            let recurrence = match repeat.try_get_text_value().unwrap().parse::<Recurrence>() {
+                Ok(r) => r,
+                Err(e) => {
+                    self.recurring_error = Some(e);
+                    return Ok(());
+                }
+            };
+            let kind = TransactionKind::from_name(kind.try_get_text_value().unwrap()).unwrap();
+            let category_id = self
+                .resolve_category(db, category.try_get_text_value().unwrap())
+                .await?;
+            let mut recurring = RecurringTransaction::new(
+                title,
+                amount,
+                "".to_owned(),
+                kind,
+                category_id,
+                recurrence,
+                next_date.try_get_date_value().unwrap().date_naive(),
+            );
+            recurring.account_id = self.target_account_id();
+            db.add_recurring_transaction(&recurring).await?;
+            self.toggle_recurring_form();
+            if db
+                .add_due_recurring_transactions(Local::now().date_naive())
+                .await?
+                > 0
+            {
+                self.refresh_transactions(db).await?;
+            }
+            self.recurring = db.get_recurring_transactions().await?;
+        }
+        Ok(())
+    }
+
+    /// Stop the selected transaction recurring
+    pub async fn delete_selected_recurring(&mut self, db: &mut DB) -> Result<(), DbError> {
+        if let Some(id) = self
+            .recurring
+            .get(self.selected_recurring as usize)
+            .map(|r| r.id)
+        {
+            db.delete_recurring_transaction(id).await?;
+            self.recurring = db.get_recurring_transactions().await?;
+            self.selected_recurring = self
+                .selected_recurring
+                .min(self.recurring.len().saturating_sub(1) as u32);
+        }
+        Ok(())
+    }
+
+    /// Account new transactions are added to, the selected one or the first
+    /// when showing all accounts
+    fn target_account_id(&self) -> i64 {
+        self.selected_account
+            .or(Some(0))
+            .and_then(|i| self.accounts.get(i))
+            .map_or(DEFAULT_ACCOUNT_ID, |a| a.id)
+    }
+
+    /// Cycle through showing all accounts then each account on its own
+    pub async fn cycle_account(&mut self, db: &mut DB) -> Result<(), DbError> {
+        self.selected_account = match self.selected_account {
+            None if !self.accounts.is_empty() => Some(0),
+            Some(i) if i + 1 < self.accounts.len() => Some(i + 1),
+            _ => None,
+        };
+        self.filters.account_id = self.selected_account.map(|i| self.accounts[i].id);
+        self.refresh_transactions(db).await
+    }
+
+    /// Opens the account form, filled in with the selected account if any
+    pub fn toggle_account_form(&mut self) {
+        self.show_account_form = !self.show_account_form;
+        if !self.show_account_form {
+            self.account_form.reset_fields();
+        } else if let Some(account) = self.selected_account.and_then(|i| self.accounts.get(i)) {
+            let values = [
+                FormValue::Text(account.name.clone()),
+                FormValue::Money(account.opening_balance),
+            ];
+            for (field, value) in self.account_form.get_fields_mut().iter_mut().zip(values) {
+                field.set_value(value);
+            }
+        }
+    }
+
+    /// Add an account or change an existing one's opening balance
+    pub async fn submit_account_form(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let fields = self.account_form.get_fields();
+        let vals: Vec<&FormValue> = fields.iter().map(|f| f.get_internal_value()).collect();
+        if let [name, opening_balance] = vals.as_slice() {
+            let name = name.try_get_text_value().unwrap().trim().to_owned();
+            if name.is_empty() {
+                return Ok(());
+            }
+            db.save_account(&name, *opening_balance.try_get_money_value().unwrap())
+                .await?;
+            self.toggle_account_form();
+            self.refresh_totals(db).await?;
+            let fields = self.transfer_form.get_fields_mut();
+            fields[TRANSFER_FROM_FIELD] =
+                Box::new(MoneyTrackerState::account_field(&self.accounts, 0, "From"));
+            fields[TRANSFER_TO_FIELD] =
+                Box::new(MoneyTrackerState::account_field(&self.accounts, 1, "To"));
+        }
+        Ok(())
+    }
+
+    pub fn toggle_transfer_form(&mut self) {
+        self.show_transfer_form = !self.show_transfer_form;
+        self.transfer_error = None;
+        if !self.show_transfer_form {
+            self.transfer_form.reset_fields();
+        }
+    }
+
+    pub async fn submit_transfer_form(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let fields = self.transfer_form.get_fields();
+        let vals: Vec<&FormValue> = fields.iter().map(|f| f.get_internal_value()).collect();
+        if let [from, to, amount, date, details] = vals.as_slice() {
+            let find = |name: &FormValue| {
+                let name = name.try_get_text_value().unwrap();
+                self.accounts.iter().find(|a| &a.name == name).cloned()
+            };
+            let (from, to) = match (find(from), find(to)) {
+                (Some(from), Some(to)) if from.id != to.id => (from, to),
+                _ => {
+                    self.transfer_error = Some("Pick two different accounts".to_owned());
+                    return Ok(());
+                }
+            };
+            let amount = *amount.try_get_money_value().unwrap();
+            if amount.minor() == 0 {
+                self.transfer_error = Some("Enter an amount".to_owned());
+                return Ok(());
+            }
+            db.add_transfer(
+                &from,
+                &to,
+                amount,
+                *date.try_get_date_value().unwrap(),
+                details.try_get_text_value().unwrap(),
+            )
+            .await?;
+            self.toggle_transfer_form();
+            self.reload_page(db).await?;
+        }
+        Ok(())
+    }
+
+    pub fn toggle_budget_form(&mut self) {
+        self.show_budget_form = !self.show_budget_form;
+        self.budget_error = None;
+        if !self.show_budget_form {
+            self.budget_form.reset_fields();
+        }
+    }
+
+    pub async fn submit_budget_form(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let fields = self.budget_form.get_fields();
+        let vals: Vec<&FormValue> = fields.iter().map(|f| f.get_internal_value()).collect();
+        if let [category, month, amount] = vals.as_slice() {
+            let month = month.try_get_text_value().unwrap().trim();
+            // stored as `YYYY-MM` so months compare in order, e.g. `2023-7`
+            // becomes `2023-07`
+            let month = match NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d") {
+                Ok(date) => date.format("%Y-%m").to_string(),
+                Err(_) => {
+                    self.budget_error = Some(format!("Invalid month: {}", month));
+                    return Ok(());
+                }
+            };
+            let category = category.try_get_text_value().unwrap();
+            let category_id = self
+                .categories
+                .iter()
+                .find(|c| &c.name == category)
+                .map(|c| c.id);
+            let amount = *amount.try_get_money_value().unwrap();
+            db.set_budget(category_id, &month, amount).await?;
+            self.toggle_budget_form();
+            self.refresh_totals(db).await?;
+        }
+        Ok(())
+    }
+
+    pub fn toggle_import_form(&mut self) {
+        self.show_import_form = !self.show_import_form;
+        self.import_error = None;
+        if !self.show_import_form {
+            self.import_form.reset_fields();
+        }
+    }
+
+    /// Read the statement named in the import form and preview its
+    /// transactions, marking any that have already been added
+    pub async fn load_import(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let path = self.import_form.get_fields()[0]
+            .get_internal_value()
+            .try_get_text_value()
+            .unwrap()
+            .clone();
+        let mut transactions = match importer::import_file(&path) {
+            Ok(t) if t.is_empty() => {
+                self.import_error = Some("No transactions found".to_owned());
+                return Ok(());
+            }
+            Ok(t) => t,
+            Err(e) => {
+                self.import_error = Some(e.to_string());
+                return Ok(());
+            }
+        };
+        RuleSet::new(db.get_category_rules().await?).categorise(&mut transactions);
+        for transaction in transactions.iter_mut() {
+            transaction.account_id = self.target_account_id();
+            transaction.category = self
+                .categories
+                .iter()
+                .find(|c| Some(c.id) == transaction.category_id)
+                .map(|c| c.name.clone());
+        }
+        let mut rows = Vec::with_capacity(transactions.len());
+        for transaction in transactions {
+            let duplicate = db.is_duplicate_transaction(&transaction).await?;
+            rows.push((transaction, duplicate, !duplicate));
+        }
+        self.toggle_import_form();
+        self.import_preview = Some(ImportPreview { rows, selected: 0 });
+        Ok(())
+    }
+
+    pub fn cancel_import(&mut self) {
+        self.import_preview = None;
+    }
+
+    /// Add the accepted transactions from the import preview
+    pub async fn confirm_import(&mut self, db: &mut DB) -> Result<(), DbError> {
+        if let Some(preview) = self.import_preview.take() {
+            let accepted: Vec<MoneyTransaction> = preview
+                .rows
+                .into_iter()
+                .filter(|(_, _, accepted)| *accepted)
+                .map(|(t, _, _)| t)
+                .collect();
+            db.add_transactions(&accepted).await?;
+            self.refresh_transactions(db).await?;
+        }
+        Ok(())
+    }
+
+    pub fn toggle_export_form(&mut self) {
+        self.show_export_form = !self.show_export_form;
+        self.export_message = None;
+        if !self.show_export_form {
+            self.export_form.reset_fields();
+        }
+    }
+
+    /// Write the transactions matching the current search, limited to the
+    /// export form's date range, to the file named in the form
+    pub async fn submit_export_form(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let fields = self.export_form.get_fields();
+        let vals: Vec<&FormValue> = fields.iter().map(|f| f.get_internal_value()).collect();
+        if let [path, from, to] = vals.as_slice() {
+            let path = path.try_get_text_value().unwrap().clone();
+            let from = exporter::parse_range_date(from.try_get_text_value().unwrap());
+            let to = exporter::parse_range_date(to.try_get_text_value().unwrap());
+            let (from, to) = match (from, to) {
+                (Ok(from), Ok(to)) => (from, to),
+                (Err(e), _) | (_, Err(e)) => {
+                    self.export_message = Some(Err(e.to_string()));
+                    return Ok(());
+                }
+            };
+            let mut filter = self.filters.clone();
+            exporter::set_date_range(&mut filter, from, to);
+            let transactions = db.get_filtered_transactions(&filter).await?;
+            let attachments = db.get_attachment_paths().await?;
+            self.export_message = Some(
+                exporter::export_file(&path, &transactions, &attachments)
+                    .map(|_| format!("Exported {} transactions", transactions.len()))
+                    .map_err(|e| e.to_string()),
+            );
+        }
+        Ok(())
+    }
+
+    /// The form key presses go to when input is being captured
+    pub fn get_active_form_mut(&mut self) -> &mut FormState {
+        if self.show_export_form {
+            &mut self.export_form
+        } else if self.show_recurring_form {
+            &mut self.recurring_form
+        } else if self.show_jump_form {
+            &mut self.jump_form
+        } else if self.show_account_form {
+            &mut self.account_form
+        } else if self.show_transfer_form {
+            &mut self.transfer_form
+        } else if self.show_import_form {
+            &mut self.import_form
+        } else if self.show_budget_form {
+            &mut self.budget_form
+        } else if self.search_form_selected {
+            &mut self.search_form
+        } else {
+            &mut self.add_form
+        }
+    }
+
+    /// Start a fuzzy search over all the transactions matching the filters
+    pub async fn open_fuzzy_search(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let transactions = db.get_filtered_transactions(&self.filters).await?;
+        self.fuzzy_search = Some(FuzzySearch::new(transactions));
+        self.fuzzy_selected = 0;
+        Ok(())
+    }
+
+    pub fn close_fuzzy_search(&mut self) {
+        self.fuzzy_search = None;
+    }
+
+    /// Update the query as it is typed, the best match is selected each time
+    pub fn send_fuzzy_input(&mut self, key: &KeyCode) {
+        if let Some(search) = &mut self.fuzzy_search {
+            match key {
+                KeyCode::Char(c) => search.push(*c),
+                KeyCode::Backspace => search.pop(),
+                _ => return,
+            }
+            self.fuzzy_selected = 0;
+        }
+    }
+
+    pub fn increment_fuzzy_selected(&mut self, amount: i32) {
+        let num_matches = self
+            .fuzzy_search
+            .as_ref()
+            .map_or(0, |s| s.get_matches().len());
+        if num_matches == 0 {
+            return;
+        }
+        let mut selected = self.fuzzy_selected as u32;
+        generic_increment(&mut selected, 0, num_matches as u32 - 1, amount);
+        self.fuzzy_selected = selected as usize;
+    }
+
+    /// Close the search and show the page starting at the selected match
+    pub async fn submit_fuzzy_search(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let cursor = self.fuzzy_search.take().and_then(|search| {
+            let m = search.get_matches().get(self.fuzzy_selected)?;
+            Some(TransactionCursor::at(search.get_transaction(m)))
+        });
+        if let Some(cursor) = cursor {
+            self.page_start = Some(cursor);
+            self.selected_transaction = 0;
+            self.load_page(db).await?;
+        }
+        Ok(())
+    }
+
+    /// Show the selected transaction with its attachments
+    pub async fn open_detail_popup(&mut self, db: &mut DB) -> Result<(), DbError> {
+        if self.transactions.is_empty() {
+            return Ok(());
+        }
+        self.attachments = db
+            .get_attachments(self.get_selected_transaction().id)
+            .await?;
+        self.selected_attachment = 0;
+        self.attachment_error = None;
+        self.show_detail_popup = true;
+        Ok(())
+    }
+
+    pub fn close_detail_popup(&mut self) {
+        self.show_detail_popup = false;
+    }
+
+    pub fn increment_selected_attachment(&mut self, amount: i32) {
+        if self.attachments.is_empty() {
+            return;
+        }
+        let mut selected = self.selected_attachment as u32;
+        generic_increment(&mut selected, 0, self.attachments.len() as u32 - 1, amount);
+        self.selected_attachment = selected as usize;
+    }
+
+    pub fn toggle_attachment_form(&mut self) {
+        self.show_attachment_form = !self.show_attachment_form;
+        self.attachment_error = None;
+        if !self.show_attachment_form {
+            self.attachment_form.reset_fields();
+        }
+    }
+
+    /// Attach the file named in the form to the transaction in the detail popup
+    pub async fn submit_attachment_form(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let path = self.attachment_form.get_fields()[0]
+            .get_internal_value()
+            .try_get_text_value()
+            .unwrap();
+        let path = expand_home(path.trim());
+        if !path.is_file() {
+            self.attachment_error = Some(format!("No file at {}", path.display()));
+            return Ok(());
+        }
+        let path = path.canonicalize().unwrap_or(path);
+        let id = self.get_selected_transaction().id;
+        db.add_attachment(id, &path.to_string_lossy()).await?;
+        self.toggle_attachment_form();
+        self.attachments = db.get_attachments(id).await?;
+        self.selected_attachment = self.attachments.len() - 1;
+        Ok(())
+    }
+
+    pub async fn delete_selected_attachment(&mut self, db: &mut DB) -> Result<(), DbError> {
+        if let Some(attachment) = self.attachments.get(self.selected_attachment) {
+            db.delete_attachment(attachment.id).await?;
+            self.attachments = db
+                .get_attachments(self.get_selected_transaction().id)
+                .await?;
+            self.selected_attachment = self
+                .selected_attachment
+                .min(self.attachments.len().saturating_sub(1));
+        }
+        Ok(())
+    }
+
+    /// Open the selected attachment with `xdg-open`
+    pub fn open_selected_attachment(&mut self) {
+        if let Some(attachment) = self.attachments.get(self.selected_attachment) {
+            self.attachment_error = open_file(&attachment.path)
+                .err()
+                .map(|e| format!("Could not open {}: {}", attachment.path, e));
+        }
+    }
+
+    pub fn toggle_delete_popup(&mut self) {
+        self.show_delete_popup = !self.show_delete_popup && !self.transactions.is_empty();
+    }
+
+    pub async fn delete_selected_transaction(&mut self, db: &mut DB) -> Result<(), DbError> {
+        self.show_delete_popup = false;
+        if self.transactions.is_empty() {
+            return Ok(());
+        }
+        db.delete_transaction(self.get_selected_transaction().id)
+            .await?;
+        self.reload_page(db).await
     }
 
     pub fn get_selected_transaction(&self) -> &MoneyTransaction {
@@ -220,13 +1430,23 @@ impl MoneyTrackerState {
 impl StatefulWidget for MoneyTracker {
     type State = MoneyTrackerState;
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        // budgets go under the transactions, taking up to a third of the height
+        let budgets_height = if state.budgets.is_empty() {
+            0
+        } else {
+            (state.budgets.len() as u16 * 3 + 2).min(area.height / 3)
+        };
         let right_pane = Rect {
             x: area.x + area.width / 3,
             y: area.y,
             width: area.width - area.width / 3,
-            height: area.height,
+            height: area.height - budgets_height,
+        };
+        let budgets_rect = Rect {
+            y: right_pane.y + right_pane.height,
+            height: budgets_height,
+            ..right_pane
         };
-        state.max_transactions = Some(right_pane.height as u32);
 
         let search_form_rect = Rect {
             x: area.x,
@@ -299,7 +1519,19 @@ impl StatefulWidget for MoneyTracker {
         buf.set_string(
             add_form_rect.x,
             add_form_rect.y - 1,
-            format!("{:^1$}", "Add", add_form_rect.width as usize),
+            format!(
+                "{:^1$}",
+                if state.editing.is_some() {
+                    "Edit".to_owned()
+                } else if state.accounts.len() > 1 {
+                    let account_id = state.target_account_id();
+                    let account = state.accounts.iter().find(|a| a.id == account_id);
+                    format!("Add to {}", account.map_or("", |a| a.name.as_str()))
+                } else {
+                    "Add".to_owned()
+                },
+                add_form_rect.width as usize
+            ),
             add_form_style.add_modifier(Modifier::BOLD),
         );
         for field in state.add_form.get_fields_mut() {
@@ -333,91 +1565,890 @@ impl StatefulWidget for MoneyTracker {
             width: right_pane.width - 2,
             height: right_pane.height - 2,
         };
+        // the account switcher takes the top line
+        render_accounts(right_pane, buf, state);
+        let right_pane = Rect {
+            y: right_pane.y + 1,
+            height: right_pane.height.saturating_sub(1),
+            ..right_pane
+        };
 
-        let mut day_indexes: Vec<usize> = vec![];
-        for (i, transaction) in state.transactions.iter().enumerate() {
-            // first transaction always has different date
-            if i == 0 {
-                day_indexes.push(i);
-            } else if state
-                .transactions
-                .get(i - 1)
-                .unwrap()
-                .date
-                .num_days_from_ce()
-                != transaction.date.num_days_from_ce()
-            {
-                day_indexes.push(i);
+        match &state.stats {
+            _ if state.fuzzy_search.is_some() => render_fuzzy_search(right_pane, buf, state),
+            Some(stats) if state.show_stats => {
+                render_stats(right_pane, buf, stats, state.stats_period)
             }
+            _ if state.show_subscriptions => render_subscriptions(right_pane, buf, state),
+            _ => render_transaction_list(right_pane, buf, state),
         }
 
-        let mut sub_heading_count = 0;
-        for (i, transaction) in state.transactions.iter().enumerate() {
-            let num_of_days = transaction.date.num_days_from_ce();
-            let mut offset_y = right_pane.y + (1 * i as u16) + (1 * sub_heading_count);
-            if offset_y >= right_pane.y + right_pane.height - 1 {
-                if state
-                    .page_offsets
-                    .get(state.current_page as usize)
-                    .is_none()
-                {
-                    state
-                        .page_offsets
-                        .push(state.page_offsets.iter().sum::<u32>() + i as u32);
+        if budgets_height > 0 {
+            draw_rect_borders(
+                buf,
+                budgets_rect,
+                Borders::ALL,
+                BorderType::Plain,
+                AppStyles::Accent.get(),
+            );
+            let title_text = format!(" Budgets {} ", Local::now().format("%B %Y"));
+            buf.set_string(
+                budgets_rect.x + 1,
+                budgets_rect.y,
+                &title_text,
+                AppStyles::TitleText.get(),
+            );
+            let budgets_rect = Rect {
+                x: budgets_rect.x + 1,
+                y: budgets_rect.y + 1,
+                width: budgets_rect.width - 2,
+                height: budgets_rect.height - 2,
+            };
+            for (i, (budget, spent)) in state
+                .budgets
+                .iter()
+                .take(budgets_rect.height as usize / 3)
+                .enumerate()
+            {
+                let title = format!(
+                    "{} {} / {}",
+                    budget.category.as_deref().unwrap_or(OVERALL_BUDGET),
+                    spent.format(false),
+                    budget.amount.format(false)
+                );
+                let progress = (spent.minor() * 100 / budget.amount.minor()).clamp(0, 100);
+                let mut bar = ProgressBar::new(title, progress as u16);
+                if spent > &budget.amount {
+                    bar = bar.style(AppStyles::ProgressBarWarning.get());
                 }
-                break;
+                bar.get_gauge().render(
+                    Rect {
+                        y: budgets_rect.y + 3 * i as u16,
+                        height: 3,
+                        ..budgets_rect
+                    },
+                    buf,
+                );
             }
-            // can binary search because always in order
-            if day_indexes.binary_search(&i).is_ok() {
-                if state.get_selected_transaction().date.num_days_from_ce() == num_of_days {
-                    buf.set_string(
-                        right_pane.x,
-                        offset_y,
-                        format!(
-                            "{:━^1$}",
-                            transaction.date.format("%a %d %B %Y").to_string(),
-                            right_pane.width as usize
-                        ),
-                        AppStyles::Main.get(),
-                    );
-                } else {
-                    buf.set_string(
-                        right_pane.x,
-                        offset_y,
-                        format!(
-                            "{:-^1$}",
-                            transaction.date.format("%a %d %B %Y").to_string(),
-                            right_pane.width as usize
-                        ),
-                        AppStyles::Accent.get(),
-                    );
-                }
-                sub_heading_count += 1;
-                offset_y += 1;
+        }
+
+        if state.show_budget_form {
+            let message = state
+                .budget_error
+                .as_deref()
+                .map(|e| Span::styled(e, AppStyles::Expense.get()));
+            render_form_popup(
+                centered_rect(40, 50, area),
+                buf,
+                " Set Budget ",
+                &mut state.budget_form,
+                message,
+            );
+        }
+
+        if state.show_jump_form {
+            let message = state
+                .jump_error
+                .as_deref()
+                .map(|e| Span::styled(e, AppStyles::Expense.get()));
+            render_form_popup(
+                centered_rect(30, 20, area),
+                buf,
+                " Jump To Date ",
+                &mut state.jump_form,
+                message,
+            );
+        }
+
+        if state.show_account_form {
+            render_form_popup(
+                centered_rect(40, 30, area),
+                buf,
+                " Account ",
+                &mut state.account_form,
+                None,
+            );
+        }
+
+        if state.show_transfer_form {
+            let message = state
+                .transfer_error
+                .as_deref()
+                .map(|e| Span::styled(e, AppStyles::Expense.get()));
+            render_form_popup(
+                centered_rect(40, 60, area),
+                buf,
+                " Transfer ",
+                &mut state.transfer_form,
+                message,
+            );
+        }
+
+        if state.show_recurring_form {
+            let message = state
+                .recurring_error
+                .as_deref()
+                .map(|e| Span::styled(e, AppStyles::Expense.get()));
+            render_form_popup(
+                centered_rect(50, 70, area),
+                buf,
+                " New Recurring Transaction ",
+                &mut state.recurring_form,
+                message,
+            );
+        }
+
+        if state.show_import_form {
+            let message = state
+                .import_error
+                .as_deref()
+                .map(|e| Span::styled(e, AppStyles::Expense.get()));
+            render_form_popup(
+                centered_rect(50, 30, area),
+                buf,
+                " Import Statement ",
+                &mut state.import_form,
+                message,
+            );
+        }
+
+        if state.show_export_form {
+            let message = state.export_message.as_ref().map(|m| match m {
+                Ok(m) => Span::styled(m.as_str(), AppStyles::Income.get()),
+                Err(e) => Span::styled(e.as_str(), AppStyles::Expense.get()),
+            });
+            render_form_popup(
+                centered_rect(50, 50, area),
+                buf,
+                " Export Transactions ",
+                &mut state.export_form,
+                message,
+            );
+        }
+
+        if let Some(preview) = &state.import_preview {
+            render_import_preview(centered_rect(70, 70, area), buf, preview);
+        }
+
+        if state.show_detail_popup && !state.transactions.is_empty() {
+            render_detail_popup(area, buf, state);
+            if state.show_attachment_form {
+                let message = state
+                    .attachment_error
+                    .as_deref()
+                    .map(|e| Span::styled(e, AppStyles::Expense.get()));
+                render_form_popup(
+                    centered_rect(50, 20, area),
+                    buf,
+                    " Attach File ",
+                    &mut state.attachment_form,
+                    message,
+                );
             }
-            let style = if i == state.selected_transaction as usize {
-                AppStyles::Main.get()
-            } else {
-                AppStyles::Accent.get()
-            };
-            buf.set_string(
-                right_pane.x,
-                offset_y,
-                format!("£{:<10}", format!("{:.2}", transaction.amount)),
-                style,
+        }
+
+        if state.show_delete_popup && !state.transactions.is_empty() {
+            let transaction = state.get_selected_transaction();
+            let lines = vec![
+                Spans::from(Span::styled(
+                    "Delete transaction?",
+                    AppStyles::TitleText.get(),
+                )),
+                Spans::from(""),
+                Spans::from(Span::styled(
+                    format!("{} {}", transaction.format_amount(), transaction.title),
+                    AppStyles::Main.get(),
+                )),
+                Spans::from(Span::styled(
+                    transaction.date.format("%a %d %B %Y").to_string(),
+                    AppStyles::Main.get(),
+                )),
+                Spans::from(""),
+                Spans::from(Span::styled("(y)es / (n)o", AppStyles::Accent.get())),
+            ];
+            let p = Paragraph::new(lines)
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: false });
+            Popup::new(p, 40, 30).render(area, buf);
+        }
+    }
+}
+
+/// Every field of the selected transaction with its attachments
+fn render_detail_popup(area: Rect, buf: &mut Buffer, state: &MoneyTrackerState) {
+    let transaction = state.get_selected_transaction();
+    let field = |name: &str, value: String| {
+        Spans::from(vec![
+            Span::styled(format!("{}: ", name), AppStyles::Accent.get()),
+            Span::styled(value, AppStyles::Main.get()),
+        ])
+    };
+    let mut lines = vec![
+        Spans::from(Span::styled(
+            transaction.title.clone(),
+            AppStyles::TitleText.get(),
+        )),
+        Spans::from(""),
+        field(
+            "Amount",
+            format!(
+                "{} ({})",
+                transaction.format_amount(),
+                transaction.kind.get_name()
+            ),
+        ),
+        field(
+            "Date",
+            transaction
+                .date
+                .format("%a %d %B %Y %H:%M:%S %Z")
+                .to_string(),
+        ),
+    ];
+    if let Some(category) = &transaction.category {
+        lines.push(field("Category", category.clone()));
+    }
+    // only worth showing when there is more than one
+    if state.accounts.len() > 1 {
+        if let Some(account) = state
+            .accounts
+            .iter()
+            .find(|a| a.id == transaction.account_id)
+        {
+            lines.push(field("Account", account.name.clone()));
+        }
+    }
+    if transaction.transfer_id.is_some() {
+        lines.push(field("Transfer", "between accounts".to_owned()));
+    }
+    if !transaction.details.is_empty() {
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(Span::styled(
+            transaction.details.clone(),
+            AppStyles::Main.get(),
+        )));
+    }
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(Span::styled(
+        "Attachments",
+        AppStyles::TitleText.get(),
+    )));
+    if state.attachments.is_empty() {
+        lines.push(Spans::from(Span::styled("None", AppStyles::Accent.get())));
+    }
+    for (i, attachment) in state.attachments.iter().enumerate() {
+        let style = if i == state.selected_attachment {
+            AppStyles::Main.get()
+        } else {
+            AppStyles::Accent.get()
+        };
+        lines.push(Spans::from(Span::styled(attachment.path.clone(), style)));
+    }
+    lines.push(Spans::from(""));
+    if let (Some(error), false) = (&state.attachment_error, state.show_attachment_form) {
+        lines.push(Spans::from(Span::styled(
+            error.clone(),
+            AppStyles::Expense.get(),
+        )));
+    }
+    lines.push(Spans::from(Span::styled(
+        "(e) edit / (d) duplicate / (x) delete / (esc) close",
+        AppStyles::Accent.get(),
+    )));
+    lines.push(Spans::from(Span::styled(
+        "attachments: (o) open / (n) add / (r) remove",
+        AppStyles::Accent.get(),
+    )));
+    let p = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: false });
+    Popup::new(p, 60, 70).render(area, buf);
+}
+
+/// One line of account balances, the selected account (or all) highlighted
+fn render_accounts(area: Rect, buf: &mut Buffer, state: &MoneyTrackerState) {
+    let total = state
+        .accounts
+        .iter()
+        .fold(Money::default(), |acc, a| acc + a.balance);
+    let mut x = area.x;
+    let entries = std::iter::once((None, "All", total)).chain(
+        state
+            .accounts
+            .iter()
+            .enumerate()
+            .map(|(i, a)| (Some(i), a.name.as_str(), a.balance)),
+    );
+    for (i, (index, name, balance)) in entries.enumerate() {
+        if i > 0 {
+            let (nx, _) = buf.set_stringn(
+                x,
+                area.y,
+                " │ ",
+                (area.x + area.width).saturating_sub(x) as usize,
+                AppStyles::Accent.get(),
             );
-            // + 11 because left align width 10 + 1 for £ sign
-            buf.set_string(right_pane.x + 11, offset_y, &transaction.title, style);
+            x = nx;
+        }
+        let style = if index == state.selected_account {
+            AppStyles::Main.get().add_modifier(Modifier::BOLD)
+        } else {
+            AppStyles::Accent.get()
+        };
+        let (nx, _) = buf.set_stringn(
+            x,
+            area.y,
+            format!("{} {}", name, balance.format(false)),
+            (area.x + area.width).saturating_sub(x) as usize,
+            style,
+        );
+        x = nx;
+    }
+}
+
+/// Spans for `text` with the characters at `indices` highlighted
+fn highlight_matches(text: &str, indices: &[usize], style: Style) -> Vec<Span<'static>> {
+    let highlight = AppStyles::TitleText.get();
+    let mut spans: Vec<Span> = vec![];
+    let mut current = String::new();
+    let mut current_matched = false;
+    for (i, c) in text.chars().enumerate() {
+        let matched = indices.contains(&i);
+        if matched != current_matched && !current.is_empty() {
+            let style = if current_matched { highlight } else { style };
+            spans.push(Span::styled(std::mem::take(&mut current), style));
+        }
+        current_matched = matched;
+        current.push(c);
+    }
+    if !current.is_empty() {
+        let style = if current_matched { highlight } else { style };
+        spans.push(Span::styled(current, style));
+    }
+    spans
+}
+
+/// Draw the query and the best matches of the fuzzy search with the matched
+/// characters highlighted
+fn render_fuzzy_search(area: Rect, buf: &mut Buffer, state: &MoneyTrackerState) {
+    let search = match &state.fuzzy_search {
+        Some(search) => search,
+        None => return,
+    };
+    buf.set_stringn(
+        area.x,
+        area.y,
+        format!("Search: {}█", search.get_query()),
+        area.width as usize,
+        AppStyles::Main.get(),
+    );
+    // first line for the query, last for the key help
+    let height = area.height.saturating_sub(2) as usize;
+    let matches = search.get_matches();
+    let start = (state.fuzzy_selected + 1).saturating_sub(height);
+    for (i, m) in matches.iter().enumerate().skip(start).take(height) {
+        let transaction = search.get_transaction(m);
+        let y = area.y + 1 + (i - start) as u16;
+        let style = if i == state.fuzzy_selected {
+            AppStyles::Main.get()
+        } else {
+            AppStyles::Accent.get()
+        };
+        let FuzzyMatch {
+            title_indices,
+            details_indices,
+            ..
+        } = m;
+        let mut spans = vec![
+            Span::styled(
+                format!("{:<12}", transaction.format_amount()),
+                match transaction.kind {
+                    TransactionKind::Income => AppStyles::Income.get(),
+                    TransactionKind::Expense => AppStyles::Expense.get(),
+                },
+            ),
+            Span::styled(
+                format!("{} ", transaction.date.format("%d/%m/%y")),
+                AppStyles::Accent.get(),
+            ),
+        ];
+        spans.extend(highlight_matches(&transaction.title, title_indices, style));
+        // only show the details when they are why it matched
+        if !details_indices.is_empty() {
+            spans.push(Span::styled(" · ", AppStyles::Accent.get()));
+            spans.extend(highlight_matches(
+                &transaction.details,
+                details_indices,
+                AppStyles::Accent.get(),
+            ));
         }
+        buf.set_spans(area.x, y, &Spans::from(spans), area.width);
+    }
+    buf.set_stringn(
+        area.x,
+        area.y + area.height - 1,
+        format!(
+            "{:^1$}",
+            format!("{} matches / (enter) go to / (esc) cancel", matches.len()),
+            area.width as usize
+        ),
+        area.width as usize,
+        AppStyles::Accent.get(),
+    );
+}
+
+/// Draw the current page of transactions grouped under day headings
+fn render_transaction_list(right_pane: Rect, buf: &mut Buffer, state: &mut MoneyTrackerState) {
+    // the last line is kept for the position in the list
+    state.list_height = Some(right_pane.height.saturating_sub(1) as u32);
+    let num_visible = state.num_visible();
+    state.selected_transaction = state
+        .selected_transaction
+        .min(num_visible.saturating_sub(1) as u32);
+
+    let mut day_indexes: Vec<usize> = vec![];
+    for (i, transaction) in state.transactions.iter().enumerate() {
+        // first transaction always has different date
+        if i == 0 {
+            day_indexes.push(i);
+        } else if state
+            .transactions
+            .get(i - 1)
+            .unwrap()
+            .date
+            .num_days_from_ce()
+            != transaction.date.num_days_from_ce()
+        {
+            day_indexes.push(i);
+        }
+    }
+
+    let mut sub_heading_count = 0;
+    for (i, transaction) in state.transactions.iter().take(num_visible).enumerate() {
+        let num_of_days = transaction.date.num_days_from_ce();
+        let mut offset_y = right_pane.y + (1 * i as u16) + (1 * sub_heading_count);
+        // can binary search because always in order
+        if day_indexes.binary_search(&i).is_ok() {
+            let day = transaction.date.date_naive();
+            let heading = match state.day_balances.get(&day) {
+                Some((total, balance)) => format!(
+                    " {} | {} | balance {} ",
+                    transaction.date.format("%a %d %B %Y"),
+                    total.format(true),
+                    balance.format(false)
+                ),
+                None => transaction.date.format("%a %d %B %Y").to_string(),
+            };
+            if state.get_selected_transaction().date.num_days_from_ce() == num_of_days {
+                buf.set_string(
+                    right_pane.x,
+                    offset_y,
+                    format!("{:━^1$}", heading, right_pane.width as usize),
+                    AppStyles::Main.get(),
+                );
+            } else {
+                buf.set_string(
+                    right_pane.x,
+                    offset_y,
+                    format!("{:-^1$}", heading, right_pane.width as usize),
+                    AppStyles::Accent.get(),
+                );
+            }
+            sub_heading_count += 1;
+            offset_y += 1;
+        }
+        let style = if i == state.selected_transaction as usize {
+            AppStyles::Main.get()
+        } else {
+            AppStyles::Accent.get()
+        };
+        let amount_style = match transaction.kind {
+            TransactionKind::Income => AppStyles::Income.get(),
+            TransactionKind::Expense => AppStyles::Expense.get(),
+        };
         buf.set_string(
             right_pane.x,
-            right_pane.y + right_pane.height - 1,
+            offset_y,
+            format!("{:<12}", transaction.format_amount()),
+            amount_style,
+        );
+        // + 12 because left align width 12 for the signed amount
+        buf.set_string(right_pane.x + 12, offset_y, &transaction.title, style);
+        let category = match (&transaction.category, transaction.transfer_id) {
+            (Some(category), _) => Some(category.as_str()),
+            (None, Some(_)) => Some("Transfer"),
+            (None, None) => None,
+        };
+        let mut right_x = right_pane.x + right_pane.width;
+        if let Some(category) = category {
+            right_x = right_x.saturating_sub(category.chars().count() as u16);
+            buf.set_string(right_x, offset_y, category, style);
+        }
+        if state.is_missing_rate(transaction) {
+            let flag = "no rate, not in totals ";
+            right_x = right_x.saturating_sub(flag.len() as u16);
+            buf.set_string(right_x, offset_y, flag, AppStyles::Expense.get());
+        }
+    }
+    buf.set_string(
+        right_pane.x,
+        right_pane.y + right_pane.height - 1,
+        format!(
+            "{:^1$}",
             format!(
-                "{:^1$}",
-                format!("{}/{}", state.current_page, state.num_of_pages),
-                right_pane.width as usize,
+                "{}-{} of {} ({})",
+                state.num_before_page + (num_visible > 0) as u32,
+                state.num_before_page + num_visible as u32,
+                state.num_of_transactions,
+                state.filters.sort.get_name()
+            ),
+            right_pane.width as usize,
+        ),
+        AppStyles::Main.get(),
+    );
+}
+
+/// How many of the transactions fit in `height` lines when listed in order
+/// with a heading before each day, the same number fit when listed in reverse
+fn num_that_fit(transactions: &[MoneyTransaction], height: u32) -> usize {
+    let mut lines = 0;
+    for (i, transaction) in transactions.iter().enumerate() {
+        let new_day = i == 0
+            || transactions[i - 1].date.num_days_from_ce() != transaction.date.num_days_from_ce();
+        lines += if new_day { 2 } else { 1 };
+        if lines > height {
+            return i;
+        }
+    }
+    transactions.len()
+}
+
+/// Draw a chart of recent spending with a summary of this month underneath
+fn render_stats(area: Rect, buf: &mut Buffer, stats: &SpendingStats, period: StatsPeriod) {
+    let chart_rect = Rect {
+        height: area.height / 2,
+        ..area
+    };
+    let labels: Vec<String> = stats
+        .period_totals
+        .iter()
+        .map(|(start, _)| {
+            match period {
+                StatsPeriod::Day => start.format("%d"),
+                StatsPeriod::Week => start.format("%d/%m"),
+                StatsPeriod::Month => start.format("%b"),
+            }
+            .to_string()
+        })
+        .collect();
+    let data: Vec<(&str, u64)> = labels
+        .iter()
+        .zip(&stats.period_totals)
+        .map(|(label, (_, total))| (label.as_str(), total.major().max(0) as u64))
+        .collect();
+    let title = format!(
+        " {} spending ({}) ",
+        period.get_name(),
+        CONFIG.currency.symbol
+    );
+    BarChart::default()
+        .block(
+            Block::default()
+                .title(Span::styled(title, AppStyles::TitleText.get()))
+                .borders(Borders::ALL)
+                .border_style(AppStyles::Accent.get()),
+        )
+        .data(&data)
+        .bar_width(((chart_rect.width.saturating_sub(2) / STATS_PERIODS as u16).max(2)) - 1)
+        .bar_gap(1)
+        .bar_style(AppStyles::Main.get())
+        .value_style(AppStyles::InvertedMain.get())
+        .label_style(AppStyles::Accent.get())
+        .render(chart_rect, buf);
+
+    let categories_rect = Rect {
+        x: area.x,
+        y: area.y + chart_rect.height,
+        width: area.width / 2,
+        height: area.height - chart_rect.height - 1,
+    };
+    let summary_rect = Rect {
+        x: area.x + categories_rect.width,
+        width: area.width - categories_rect.width,
+        ..categories_rect
+    };
+
+    let mut lines = vec![
+        Spans::from(Span::styled(
+            "Top categories this month",
+            AppStyles::TitleText.get(),
+        )),
+        Spans::from(""),
+    ];
+    if stats.top_categories.is_empty() {
+        lines.push(Spans::from(Span::styled(
+            "No spending yet",
+            AppStyles::Accent.get(),
+        )));
+    }
+    for (name, total) in &stats.top_categories {
+        let total = total.format(false);
+        lines.push(Spans::from(Span::styled(
+            format!(
+                "{:<1$}{2}",
+                name,
+                (categories_rect.width as usize).saturating_sub(total.chars().count() + 2),
+                total
             ),
             AppStyles::Main.get(),
+        )));
+    }
+    Paragraph::new(lines).render(
+        Rect {
+            x: categories_rect.x + 1,
+            width: categories_rect.width.saturating_sub(1),
+            ..categories_rect
+        },
+        buf,
+    );
+
+    let change = match stats.last_month_to_date.minor() {
+        0 => "-".to_owned(),
+        last => format!(
+            "{:+.0}%",
+            (stats.this_month.minor() - last) as f64 * 100. / last as f64
+        ),
+    };
+    let width = summary_rect.width.saturating_sub(2) as usize;
+    let mut lines = vec![
+        Spans::from(Span::styled("Summary", AppStyles::TitleText.get())),
+        Spans::from(""),
+    ];
+    for (label, value) in [
+        (
+            "Average per day (30 days)",
+            stats.daily_average.format(false),
+        ),
+        ("This month so far", stats.this_month.format(false)),
+        (
+            "Last month by this date",
+            stats.last_month_to_date.format(false),
+        ),
+        ("Change", change),
+        ("Last month total", stats.last_month.format(false)),
+    ] {
+        lines.push(Spans::from(Span::styled(
+            format!(
+                "{:<1$}{2}",
+                label,
+                width.saturating_sub(value.chars().count()),
+                value
+            ),
+            AppStyles::Main.get(),
+        )));
+    }
+    Paragraph::new(lines).render(
+        Rect {
+            x: summary_rect.x + 1,
+            width: summary_rect.width.saturating_sub(2),
+            ..summary_rect
+        },
+        buf,
+    );
+
+    buf.set_string(
+        area.x,
+        area.y + area.height - 1,
+        format!(
+            "{:^1$}",
+            "(p) change period / (v) back to list", area.width as usize
+        ),
+        AppStyles::Main.get(),
+    );
+}
+
+/// Draw the recurring transactions with their monthly cost and the charges
+/// coming up soon
+fn render_subscriptions(area: Rect, buf: &mut Buffer, state: &MoneyTrackerState) {
+    let title = format!(
+        " Recurring | {} a month ",
+        state.monthly_recurring_total().format(true)
+    );
+    buf.set_string(
+        area.x,
+        area.y,
+        format!("{:━^1$}", title, area.width as usize),
+        AppStyles::Main.get(),
+    );
+
+    let today = Local::now().date_naive();
+    let upcoming = state.upcoming_recurring(today + Duration::days(UPCOMING_DAYS));
+    // the recurring transactions get the top half, upcoming charges the rest
+    let list_height = (area.height.saturating_sub(3) / 2) as usize;
+    let start = (state.selected_recurring as usize + 1).saturating_sub(list_height);
+    for (i, r) in state
+        .recurring
+        .iter()
+        .enumerate()
+        .skip(start)
+        .take(list_height)
+    {
+        let style = if i == state.selected_recurring as usize {
+            AppStyles::Main.get()
+        } else {
+            AppStyles::Accent.get()
+        };
+        let schedule = format!(
+            "{} | next {} | {} a month",
+            r.recurrence,
+            r.next_date.format("%a %d %b"),
+            r.monthly_amount().format(true)
+        );
+        let text = format!(
+            "{:<12}{:<2$}{3}",
+            r.signed_amount().format(true),
+            match &r.category {
+                Some(category) => format!("{} ({})", r.title, category),
+                None => r.title.clone(),
+            },
+            (area.width as usize).saturating_sub(12 + schedule.chars().count()),
+            schedule
+        );
+        buf.set_stringn(
+            area.x,
+            area.y + 1 + (i - start) as u16,
+            text,
+            area.width as usize,
+            style,
+        );
+    }
+    if state.recurring.is_empty() {
+        buf.set_string(
+            area.x,
+            area.y + 1,
+            "Nothing recurring yet, press n to add",
+            AppStyles::Accent.get(),
+        );
+    }
+
+    let upcoming_y = area.y + 1 + list_height as u16;
+    let upcoming_total: Money = upcoming.iter().map(|(_, r)| r.signed_amount()).sum();
+    buf.set_string(
+        area.x,
+        upcoming_y,
+        format!(
+            "{:━^1$}",
+            format!(
+                " Next {} days | {} ",
+                UPCOMING_DAYS,
+                upcoming_total.format(true)
+            ),
+            area.width as usize
+        ),
+        AppStyles::Main.get(),
+    );
+    let upcoming_height = (area.y + area.height).saturating_sub(upcoming_y + 2) as usize;
+    for (i, (date, r)) in upcoming.iter().take(upcoming_height).enumerate() {
+        let text = format!(
+            "{}  {:<12}{}",
+            date.format("%a %d %b"),
+            r.signed_amount().format(true),
+            r.title
+        );
+        buf.set_stringn(
+            area.x,
+            upcoming_y + 1 + i as u16,
+            text,
+            area.width as usize,
+            AppStyles::Accent.get(),
         );
     }
+
+    buf.set_string(
+        area.x,
+        area.y + area.height - 1,
+        format!(
+            "{:^1$}",
+            "(n) new / (x) stop recurring / (S) back", area.width as usize
+        ),
+        AppStyles::Accent.get(),
+    );
+}
+
+/// Draw the transactions waiting to be imported with whether each is accepted
+fn render_import_preview(area: Rect, buf: &mut Buffer, preview: &ImportPreview) {
+    clear_area(buf, area);
+    draw_rect_borders(
+        buf,
+        area,
+        Borders::ALL,
+        BorderType::Thick,
+        AppStyles::Main.get(),
+    );
+    let num_accepted = preview.rows.iter().filter(|(_, _, a)| *a).count();
+    let title_text = format!(
+        " Import {} of {} transactions ",
+        num_accepted,
+        preview.rows.len()
+    );
+    buf.set_string(
+        area.x + ((area.width - 2) / 2) - (title_text.len() as u16 / 2),
+        area.y,
+        &title_text,
+        AppStyles::Main.get(),
+    );
+    let area = Rect {
+        x: area.x + 1,
+        y: area.y + 1,
+        width: area.width - 2,
+        height: area.height - 2,
+    };
+
+    // keep the last line for the key help
+    let height = area.height.saturating_sub(1) as usize;
+    let start = (preview.selected + 1).saturating_sub(height);
+    for (i, (transaction, duplicate, accepted)) in
+        preview.rows.iter().enumerate().skip(start).take(height)
+    {
+        let style = if i == preview.selected {
+            AppStyles::Main.get()
+        } else {
+            AppStyles::Accent.get()
+        };
+        let flag = if *duplicate { "duplicate" } else { "" };
+        let text = format!(
+            "[{}] {} {:<12}{}{}",
+            if *accepted { "x" } else { " " },
+            transaction.date.format("%d/%m/%Y"),
+            transaction.format_amount(),
+            transaction.title,
+            transaction
+                .category
+                .as_ref()
+                .map_or("".to_owned(), |c| format!(" ({})", c)),
+        );
+        let y = area.y + (i - start) as u16;
+        buf.set_stringn(
+            area.x,
+            y,
+            &text,
+            (area.width as usize).saturating_sub(flag.len() + 1),
+            style,
+        );
+        buf.set_string(
+            (area.x + area.width).saturating_sub(flag.len() as u16),
+            y,
+            flag,
+            AppStyles::Expense.get(),
+        );
+    }
+
+    buf.set_string(
+        area.x,
+        area.y + area.height - 1,
+        format!(
+            "{:^1$}",
+            "(space) toggle / (enter) import / (esc) cancel", area.width as usize
+        ),
+        AppStyles::Accent.get(),
+    );
 }
@@ -0,0 +1,118 @@
+use std::{env, path::PathBuf};
+
+lazy_static! {
+    pub static ref PATHS: AppPaths = AppPaths::new();
+}
+
+/// Where the app keeps its data and config files
+///
+/// Each directory is taken from, in order of priority, a command line flag
+/// (`--data-dir`/`--config-dir`), an environment variable
+/// (`UTIL_TUI_DATA_DIR`/`UTIL_TUI_CONFIG_DIR`), the XDG base directory
+/// variables, and finally the XDG defaults under `$HOME`
+///
+/// Passing `--data-dir <repo>/src` uses the files from before the data
+/// directory was configurable
+///
+/// The Python helpers `get_events.py` and `search_imdb.py` are run from the
+/// data directory, installing them means copying them there from `src`, along
+/// with the Google `credentials.json` the calendar needs
+#[derive(Debug)]
+pub struct AppPaths {
+    pub data_dir: PathBuf,
+    pub config_dir: PathBuf,
+}
+
+impl AppPaths {
+    fn new() -> AppPaths {
+        AppPaths {
+            data_dir: AppPaths::resolve_dir(
+                "--data-dir",
+                "UTIL_TUI_DATA_DIR",
+                "XDG_DATA_HOME",
+                ".local/share",
+            ),
+            config_dir: AppPaths::resolve_dir(
+                "--config-dir",
+                "UTIL_TUI_CONFIG_DIR",
+                "XDG_CONFIG_HOME",
+                ".config",
+            ),
+        }
+    }
+
+    fn resolve_dir(flag: &str, env_var: &str, xdg_var: &str, home_fallback: &str) -> PathBuf {
+        if let Some(dir) = get_flag_value(flag) {
+            return PathBuf::from(dir);
+        }
+        if let Some(dir) = env::var_os(env_var).filter(|d| !d.is_empty()) {
+            return PathBuf::from(dir);
+        }
+        let base = env::var_os(xdg_var)
+            .filter(|d| !d.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(home_fallback)
+            });
+        base.join("util-tui")
+    }
+
+    pub fn get_db_path(&self) -> PathBuf {
+        self.data_dir.join("db.sqlite3")
+    }
+
+    /// Files grades were kept in before they moved into the database, imported
+    /// once on startup
+    pub fn get_legacy_grades_paths(&self) -> Vec<PathBuf> {
+        ["grades_y1.json", "grades_y2.json", "grades.json"]
+            .iter()
+            .map(|name| self.data_dir.join(name))
+            .collect()
+    }
+
+    pub fn get_script_path(&self, name: &str) -> PathBuf {
+        self.data_dir.join(name)
+    }
+
+    pub fn get_log_path(&self) -> PathBuf {
+        self.data_dir.join("log/info.log")
+    }
+
+    pub fn get_config_path(&self) -> PathBuf {
+        self.config_dir.join("config.json")
+    }
+
+    pub fn get_logging_config_path(&self) -> PathBuf {
+        self.config_dir.join("logging_config.yaml")
+    }
+}
+
+/// Get the value given to a command line flag, as either `--flag value` or
+/// `--flag=value`
+pub fn get_flag_value(flag: &str) -> Option<String> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(flag).and_then(|a| a.strip_prefix('=')) {
+            return Some(value.to_owned());
+        }
+    }
+    None
+}
+
+/// Get the command line arguments that aren't flags or their values, every
+/// flag takes a value either as the next argument or after `=`
+pub fn get_positional_args() -> Vec<String> {
+    let mut positional = vec![];
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            positional.push(arg);
+        } else if !arg.contains('=') {
+            args.next();
+        }
+    }
+    positional
+}
@@ -1,25 +1,39 @@
-use tui::widgets::{Block, Borders, Gauge};
+use tui::{
+    style::Style,
+    widgets::{Block, Borders, Gauge},
+};
 
 use crate::styles::AppStyles;
 
 pub struct ProgressBar {
     pub title: String,
     pub progress: u16,
+    pub style: Style,
 }
 
 impl ProgressBar {
     pub fn new(title: String, progress: u16) -> ProgressBar {
-        ProgressBar { title, progress }
+        ProgressBar {
+            title,
+            progress,
+            style: AppStyles::ProgressBar.get(),
+        }
     }
+
+    pub fn style(mut self, style: Style) -> ProgressBar {
+        self.style = style;
+        self
+    }
+
     pub fn get_gauge(&self) -> Gauge {
         Gauge::default()
             .block(
                 Block::default()
                     .title(&self.title[..])
-                    .style(AppStyles::ProgressBar.get())
+                    .style(self.style)
                     .borders(Borders::ALL),
             )
-            .gauge_style(AppStyles::ProgressBar.get())
+            .gauge_style(self.style)
             .percent(self.progress)
     }
 }
@@ -0,0 +1,203 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use sqlx::{
+    encode::IsNull,
+    error::BoxDynError,
+    sqlite::{SqliteArgumentValue, SqliteTypeInfo, SqliteValueRef},
+    Decode, Encode, Sqlite, Type,
+};
+
+/// Schedule rule for items that come back after being completed
+///
+/// Stored in the database as the same text the user types in, e.g. `daily`,
+/// `weekly mon,thu`, `monthly 15` or `every 3`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recurrence {
+    Daily,
+    Weekly(Vec<Weekday>),
+    Monthly(u32),
+    EveryNDays(u32),
+}
+
+impl Recurrence {
+    /// Get the first date strictly after `date` that matches the rule
+    pub fn next_after(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Recurrence::Daily => date + Duration::days(1),
+            Recurrence::EveryNDays(n) => date + Duration::days(*n as i64),
+            Recurrence::Weekly(days) => (1..=7)
+                .map(|i| date + Duration::days(i))
+                .find(|d| days.contains(&d.weekday()))
+                .unwrap_or(date + Duration::days(7)),
+            Recurrence::Monthly(day) => {
+                let this_month = clamped_day_of_month(date.year(), date.month(), *day);
+                if this_month > date {
+                    this_month
+                } else if date.month() == 12 {
+                    clamped_day_of_month(date.year() + 1, 1, *day)
+                } else {
+                    clamped_day_of_month(date.year(), date.month() + 1, *day)
+                }
+            }
+        }
+    }
+
+    /// Average number of occurrences in a year
+    pub fn times_per_year(&self) -> f64 {
+        match self {
+            Recurrence::Daily => 365.0,
+            Recurrence::Weekly(days) => 52.0 * days.len() as f64,
+            Recurrence::Monthly(_) => 12.0,
+            Recurrence::EveryNDays(n) => 365.0 / *n as f64,
+        }
+    }
+}
+
+/// Get the given day in a month, using the last day of the month if it is too
+/// short (e.g. the 31st in April becomes the 30th)
+fn clamped_day_of_month(year: i32, month: u32, day: u32) -> NaiveDate {
+    (1..=day)
+        .rev()
+        .find_map(|d| NaiveDate::from_ymd_opt(year, month, d))
+        .unwrap()
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recurrence::Daily => write!(f, "daily"),
+            Recurrence::Weekly(days) => write!(
+                f,
+                "weekly {}",
+                days.iter()
+                    .map(|d| d.to_string().to_lowercase())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
+            Recurrence::Monthly(day) => write!(f, "monthly {}", day),
+            Recurrence::EveryNDays(n) => write!(f, "every {}", n),
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let (kind, arg) = s.split_once(' ').unwrap_or((&s, ""));
+        let arg = arg.trim();
+        match kind {
+            "daily" => Ok(Recurrence::Daily),
+            "weekly" => {
+                let mut days = arg
+                    .split(',')
+                    .map(|d| d.trim().parse::<Weekday>())
+                    .collect::<Result<Vec<Weekday>, _>>()
+                    .map_err(|_| format!("invalid weekdays: {}", arg))?;
+                days.sort_by_key(|d| d.num_days_from_monday());
+                days.dedup();
+                Ok(Recurrence::Weekly(days))
+            }
+            "monthly" => match arg.parse::<u32>() {
+                Ok(day @ 1..=31) => Ok(Recurrence::Monthly(day)),
+                _ => Err(format!("invalid day of month: {}", arg)),
+            },
+            "every" => match arg.trim_end_matches("days").trim().parse::<u32>() {
+                Ok(n) if n > 0 => Ok(Recurrence::EveryNDays(n)),
+                _ => Err(format!("invalid number of days: {}", arg)),
+            },
+            _ => Err(format!("unknown recurrence: {}", s)),
+        }
+    }
+}
+
+impl Type<Sqlite> for Recurrence {
+    fn type_info() -> SqliteTypeInfo {
+        <String as Type<Sqlite>>::type_info()
+    }
+
+    fn compatible(ty: &SqliteTypeInfo) -> bool {
+        <String as Type<Sqlite>>::compatible(ty)
+    }
+}
+
+impl<'q> Encode<'q, Sqlite> for Recurrence {
+    fn encode_by_ref(&self, buf: &mut Vec<SqliteArgumentValue<'q>>) -> IsNull {
+        Encode::<Sqlite>::encode(self.to_string(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Sqlite> for Recurrence {
+    fn decode(value: SqliteValueRef<'r>) -> Result<Self, BoxDynError> {
+        Ok(<&str as Decode<Sqlite>>::decode(value)?.parse::<Recurrence>()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        for s in ["daily", "weekly mon,thu", "monthly 15", "every 3"] {
+            assert_eq!(s.parse::<Recurrence>().unwrap().to_string(), s);
+        }
+        assert_eq!(
+            "Weekly Fri, Mon".parse::<Recurrence>().unwrap(),
+            Recurrence::Weekly(vec![Weekday::Mon, Weekday::Fri])
+        );
+        assert!("monthly 32".parse::<Recurrence>().is_err());
+        assert!("every 0".parse::<Recurrence>().is_err());
+        assert!("weekly".parse::<Recurrence>().is_err());
+        assert!("yearly".parse::<Recurrence>().is_err());
+    }
+
+    #[test]
+    fn test_times_per_year() {
+        assert_eq!(Recurrence::Monthly(1).times_per_year(), 12.0);
+        assert_eq!(
+            Recurrence::Weekly(vec![Weekday::Mon, Weekday::Thu]).times_per_year(),
+            104.0
+        );
+        assert_eq!(Recurrence::EveryNDays(5).times_per_year(), 73.0);
+    }
+
+    #[test]
+    fn test_next_after() {
+        // 2023-07-05 is a Wednesday
+        let wed = date(2023, 7, 5);
+        assert_eq!(Recurrence::Daily.next_after(wed), date(2023, 7, 6));
+        assert_eq!(
+            Recurrence::EveryNDays(10).next_after(wed),
+            date(2023, 7, 15)
+        );
+        assert_eq!(
+            Recurrence::Weekly(vec![Weekday::Mon, Weekday::Wed]).next_after(wed),
+            date(2023, 7, 10)
+        );
+        assert_eq!(
+            Recurrence::Weekly(vec![Weekday::Wed]).next_after(wed),
+            date(2023, 7, 12)
+        );
+        assert_eq!(Recurrence::Monthly(15).next_after(wed), date(2023, 7, 15));
+        assert_eq!(Recurrence::Monthly(5).next_after(wed), date(2023, 8, 5));
+        assert_eq!(
+            Recurrence::Monthly(31).next_after(date(2023, 1, 31)),
+            date(2023, 2, 28)
+        );
+        assert_eq!(
+            Recurrence::Monthly(31).next_after(date(2023, 2, 28)),
+            date(2023, 3, 31)
+        );
+        assert_eq!(
+            Recurrence::Monthly(1).next_after(date(2023, 12, 1)),
+            date(2024, 1, 1)
+        );
+    }
+}
@@ -0,0 +1,348 @@
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::Span,
+    widgets::{BorderType, Borders, StatefulWidget},
+};
+
+use crate::{
+    category_rules::{CategoryRule, RuleKind},
+    db::{Category, DbError, DB},
+    form::{
+        render_form_popup, ChoiceField, FormFieldStyle, FormState, FormValue, MoneyField, TextField,
+    },
+    money::Money,
+    styles::AppStyles,
+    util::{centered_rect, draw_rect_borders, generic_increment},
+};
+
+/// Index of the category picker in the rule form
+const CATEGORY_FIELD: usize = 4;
+
+#[derive(Debug)]
+pub struct RulesEditorState {
+    pub rules: Vec<CategoryRule>,
+    pub selected: u32,
+    pub show_form: bool,
+    pub form_state: FormState,
+    categories: Vec<Category>,
+    editing: Option<i64>,
+    /// Shown under the list, e.g. how many transactions re-applying changed
+    message: Option<String>,
+    /// Shown in the form when a rule can't be saved
+    form_error: Option<String>,
+}
+
+impl RulesEditorState {
+    pub async fn new(db: &mut DB) -> Result<RulesEditorState, DbError> {
+        let max_amount = Money::from_major(100000);
+        let mut form_state = FormState::new();
+        form_state.add_field(Box::new(ChoiceField::new(
+            vec![
+                RuleKind::Contains.get_name().to_owned(),
+                RuleKind::Regex.get_name().to_owned(),
+            ],
+            RuleKind::Contains.get_name().to_owned(),
+            false,
+            true,
+            FormFieldStyle::new("Match (←/→)".to_owned()),
+        )));
+        form_state.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            false,
+            FormFieldStyle::new("Title pattern (blank matches all)".to_owned()),
+        )));
+        form_state.add_field(Box::new(MoneyField::new(
+            Money::default(),
+            max_amount,
+            false,
+            FormFieldStyle::new("Min amount (0 for none)".to_owned()),
+        )));
+        form_state.add_field(Box::new(MoneyField::new(
+            Money::default(),
+            max_amount,
+            false,
+            FormFieldStyle::new("Max amount (0 for none)".to_owned()),
+        )));
+        form_state.add_field(Box::new(RulesEditorState::category_field(&[])));
+        let mut state = RulesEditorState {
+            rules: Vec::new(),
+            selected: 0,
+            show_form: false,
+            form_state,
+            categories: Vec::new(),
+            editing: None,
+            message: None,
+            form_error: None,
+        };
+        state.refresh(db).await?;
+        Ok(state)
+    }
+
+    /// Category picker limited to the existing categories
+    fn category_field(categories: &[Category]) -> ChoiceField {
+        ChoiceField::new(
+            categories.iter().map(|c| c.name.clone()).collect(),
+            categories.first().map_or("".to_owned(), |c| c.name.clone()),
+            false,
+            true,
+            FormFieldStyle::new("Category (←/→)".to_owned()),
+        )
+    }
+
+    /// Reload the rules and the categories they can be given
+    pub async fn refresh(&mut self, db: &mut DB) -> Result<(), DbError> {
+        self.rules = db.get_category_rules().await?;
+        if self.selected as usize >= self.rules.len() {
+            self.selected = self.rules.len().saturating_sub(1) as u32;
+        }
+        let categories = db.get_categories().await?;
+        if categories
+            .iter()
+            .map(|c| c.id)
+            .ne(self.categories.iter().map(|c| c.id))
+        {
+            self.form_state.get_fields_mut()[CATEGORY_FIELD] =
+                Box::new(RulesEditorState::category_field(&categories));
+            self.categories = categories;
+        }
+        Ok(())
+    }
+
+    pub fn get_selected_rule(&self) -> Option<&CategoryRule> {
+        self.rules.get(self.selected as usize)
+    }
+
+    pub fn increment_selected(&mut self, amount: i32) {
+        if self.rules.is_empty() {
+            return;
+        }
+        generic_increment(&mut self.selected, 0, self.rules.len() as u32 - 1, amount);
+    }
+
+    pub fn toggle_form(&mut self) {
+        self.show_form = !self.show_form;
+        self.form_error = None;
+        if !self.show_form {
+            self.editing = None;
+            self.form_state.reset_fields();
+        }
+    }
+
+    /// Opens the form filled in with the selected rule so it can be edited
+    pub fn open_edit_form(&mut self) {
+        if let Some(rule) = self.get_selected_rule() {
+            let id = rule.id;
+            let values = [
+                FormValue::Text(rule.kind.get_name().to_owned()),
+                FormValue::Text(rule.pattern.clone()),
+                FormValue::Money(rule.min_amount.unwrap_or_default()),
+                FormValue::Money(rule.max_amount.unwrap_or_default()),
+                FormValue::Text(rule.category.clone().unwrap_or_default()),
+            ];
+            for (field, value) in self.form_state.get_fields_mut().iter_mut().zip(values) {
+                field.set_value(value);
+            }
+            self.editing = Some(id);
+            self.show_form = true;
+            self.form_error = None;
+        }
+    }
+
+    pub async fn submit_form(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let fields = self.form_state.get_fields();
+        let vals: Vec<&FormValue> = fields.iter().map(|f| f.get_internal_value()).collect();
+        if let [kind, pattern, min, max, category] = vals.as_slice() {
+            let kind = RuleKind::from_name(kind.try_get_text_value().unwrap()).unwrap();
+            let pattern = pattern.try_get_text_value().unwrap().trim().to_owned();
+            if let Err(e) = CategoryRule::compile(kind, &pattern) {
+                // regex errors are laid out over several lines
+                let e = e
+                    .to_string()
+                    .split_whitespace()
+                    .collect::<Vec<&str>>()
+                    .join(" ");
+                self.form_error = Some(format!("Invalid regex: {}", e));
+                return Ok(());
+            }
+            let min = Some(*min.try_get_money_value().unwrap()).filter(|m| m.minor() > 0);
+            let max = Some(*max.try_get_money_value().unwrap()).filter(|m| m.minor() > 0);
+            if let (Some(min), Some(max)) = (min, max) {
+                if min > max {
+                    self.form_error = Some("Min amount is more than max amount".to_owned());
+                    return Ok(());
+                }
+            }
+            let category = category.try_get_text_value().unwrap();
+            let category_id = match self.categories.iter().find(|c| &c.name == category) {
+                Some(c) => c.id,
+                None => {
+                    self.form_error = Some("Add a category on the money screen first".to_owned());
+                    return Ok(());
+                }
+            };
+            let mut rule = CategoryRule::new(kind, pattern, min, max, category_id);
+            match self.editing {
+                Some(id) => {
+                    rule.id = id;
+                    db.update_category_rule(&rule).await?;
+                }
+                None => db.add_category_rule(&rule).await?,
+            }
+            self.toggle_form();
+            self.refresh(db).await?;
+        }
+        Ok(())
+    }
+
+    /// Moves the selected rule up or down, rules higher in the list are tried
+    /// first
+    pub async fn move_selected(&mut self, db: &mut DB, amount: i32) -> Result<(), DbError> {
+        let cur = self.selected as usize;
+        if cur >= self.rules.len() {
+            return Ok(());
+        }
+        let other = if amount.is_negative() {
+            match cur.checked_sub(1) {
+                Some(i) => i,
+                None => return Ok(()),
+            }
+        } else if cur + 1 < self.rules.len() {
+            cur + 1
+        } else {
+            return Ok(());
+        };
+        db.swap_category_rule_positions(&self.rules[cur], &self.rules[other])
+            .await?;
+        self.selected = other as u32;
+        self.refresh(db).await
+    }
+
+    pub async fn delete_selected(&mut self, db: &mut DB) -> Result<(), DbError> {
+        if let Some(id) = self.get_selected_rule().map(|r| r.id) {
+            db.delete_category_rule(id).await?;
+            self.refresh(db).await?;
+        }
+        Ok(())
+    }
+
+    /// Run the rules over all existing transactions
+    pub async fn reapply(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let changed = db.apply_category_rules().await?;
+        self.message = Some(format!("Re-categorised {} transactions", changed));
+        Ok(())
+    }
+}
+
+pub struct RulesEditor;
+
+impl RulesEditor {
+    pub fn new() -> RulesEditor {
+        RulesEditor {}
+    }
+}
+
+impl StatefulWidget for RulesEditor {
+    type State = RulesEditorState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        draw_rect_borders(
+            buf,
+            area,
+            Borders::ALL,
+            BorderType::Plain,
+            AppStyles::Main.get(),
+        );
+        let area = Rect {
+            x: area.x + 1,
+            y: area.y + 1,
+            width: area.width - 2,
+            height: area.height - 2,
+        };
+
+        buf.set_string(
+            area.x,
+            area.y,
+            format!("{:━^1$}", "Category Rules", area.width as usize),
+            AppStyles::Main.get(),
+        );
+        // keep the last two lines for the message and key help
+        let height = area.height.saturating_sub(3) as usize;
+        let start = (state.selected as usize + 1).saturating_sub(height);
+        for (i, rule) in state.rules.iter().enumerate().skip(start).take(height) {
+            let style = if i == state.selected as usize {
+                AppStyles::Main.get()
+            } else {
+                AppStyles::Accent.get()
+            };
+            let amount_text = match (rule.min_amount, rule.max_amount) {
+                (Some(min), Some(max)) => format!(" {} to {}", min, max),
+                (Some(min), None) => format!(" at least {}", min),
+                (None, Some(max)) => format!(" up to {}", max),
+                (None, None) => "".to_owned(),
+            };
+            let text = format!(
+                "{:>3}. {:<8} {:<30}{:<28}→ {}",
+                i + 1,
+                rule.kind.get_name(),
+                format!("\"{}\"", rule.pattern),
+                amount_text,
+                rule.category.as_deref().unwrap_or(""),
+            );
+            buf.set_stringn(
+                area.x,
+                area.y + 1 + (i - start) as u16,
+                text,
+                area.width as usize,
+                style,
+            );
+        }
+        if state.rules.is_empty() {
+            buf.set_string(
+                area.x,
+                area.y + 1,
+                "No rules yet, press i to add one",
+                AppStyles::Accent.get(),
+            );
+        }
+
+        if let Some(message) = &state.message {
+            buf.set_stringn(
+                area.x,
+                area.y + area.height - 2,
+                message,
+                area.width as usize,
+                AppStyles::Main.get(),
+            );
+        }
+        buf.set_stringn(
+            area.x,
+            area.y + area.height - 1,
+            format!(
+                "{:^1$}",
+                "(i) add / (e) edit / (x) delete / (K/J) move / (a) re-apply to all / (esc) back",
+                area.width as usize
+            ),
+            area.width as usize,
+            AppStyles::Accent.get(),
+        );
+
+        if state.show_form {
+            let title_text = if state.editing.is_some() {
+                " Edit Rule "
+            } else {
+                " New Rule "
+            };
+            render_form_popup(
+                centered_rect(50, 70, area),
+                buf,
+                title_text,
+                &mut state.form_state,
+                state
+                    .form_error
+                    .as_ref()
+                    .map(|e| Span::styled(e.clone(), AppStyles::Expense.get())),
+            );
+        }
+    }
+}
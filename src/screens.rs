@@ -1,3 +1,4 @@
+use chrono::Local;
 use tui::{
     backend::Backend,
     layout::{Alignment, Rect},
@@ -10,12 +11,15 @@ use crate::{
     app::App,
     calendar::{Calendar, CalendarEvent},
     clock::Clock,
+    exchange_rates::ExchangeRatesEditor,
     film_tracker::FilmTracker,
     grade_tracker::GradeTracker,
     money_tracker::MoneyTracker,
     popup::Popup,
     progress_bar::ProgressBar,
+    rules_editor::RulesEditor,
     styles::AppStyles,
+    todo_list::TodoList,
 };
 
 pub enum Screen {
@@ -24,6 +28,9 @@ pub enum Screen {
     GradeScreen,
     MoneyScreen,
     FilmScreen,
+    TodoScreen,
+    RulesScreen,
+    RatesScreen,
 }
 
 impl Screen {
@@ -34,10 +41,36 @@ impl Screen {
             Screen::GradeScreen => grade_screen,
             Screen::MoneyScreen => money_screen,
             Screen::FilmScreen => film_screen,
+            Screen::TodoScreen => todo_screen,
+            Screen::RulesScreen => rules_screen,
+            Screen::RatesScreen => rates_screen,
         }
     }
 }
 
+/// Show an error over the current screen until a key is pressed
+pub fn error_popup<B: Backend>(f: &mut Frame<B>, message: &str) {
+    let mut lines = vec![
+        Spans::from(Span::styled("Error", AppStyles::TitleText.get())),
+        Spans::from(""),
+    ];
+    lines.extend(
+        message
+            .lines()
+            .map(|l| Spans::from(Span::styled(l, AppStyles::Main.get()))),
+    );
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(Span::styled(
+        "Press any key to continue",
+        AppStyles::Accent.get(),
+    )));
+    let p = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: false });
+    let popup = Popup::new(p, 50, 30);
+    f.render_widget(popup, f.size());
+}
+
 fn dashboard_screen<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     let clock = Clock::new(true);
 
@@ -91,11 +124,36 @@ fn dashboard_screen<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         height: area.height - area.height / 3,
     };
 
-    let empty_block = Block::default()
-        .borders(Borders::ALL)
-        .title("TODO")
-        .style(AppStyles::Main.get());
-    f.render_widget(empty_block, block_rect);
+    // overdue and due today first, then everything else still outstanding
+    let today = Local::now().date_naive();
+    let due_todos = app.todo_state.get_due_todos(today);
+    let todo_lines: Vec<Spans> = due_todos
+        .iter()
+        .map(|t| {
+            let due_text = match t.due_date {
+                Some(d) if d < today => format!(" (overdue {})", d.format("%a %d %b")),
+                _ => " (today)".to_owned(),
+            };
+            Spans::from(vec![
+                Span::styled(&t.description, AppStyles::TitleText.get()),
+                Span::styled(due_text, AppStyles::Main.get()),
+            ])
+        })
+        .chain(
+            app.todo_state
+                .todos
+                .iter()
+                .filter(|t| !t.is_completed() && !t.is_due(today))
+                .map(|t| Spans::from(Span::styled(&t.description, AppStyles::Accent.get()))),
+        )
+        .collect();
+    let todo_block = Paragraph::new(todo_lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("TODO")
+            .style(AppStyles::Main.get()),
+    );
+    f.render_widget(todo_block, block_rect);
 
     let block_rect = Rect {
         x: area.width / 2,
@@ -104,15 +162,41 @@ fn dashboard_screen<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         height: area.height / 3,
     };
 
-    let empty_block = Block::default()
-        .borders(Borders::ALL)
-        .title("Calendar")
-        .style(AppStyles::Main.get());
-    f.render_widget(empty_block, block_rect);
+    // assessments due from today on
+    let deadline_lines: Vec<Spans> = app
+        .grade_state
+        .get_deadlines()
+        .into_iter()
+        .filter(|d| d.date >= today)
+        .map(|d| {
+            let days = (d.date - today).num_days();
+            let due_text = match days {
+                0 => " (today)".to_owned(),
+                1 => " (tomorrow)".to_owned(),
+                _ => format!(" ({} days)", days),
+            };
+            Spans::from(vec![
+                Span::styled(
+                    d.date.format("%a %d %b ").to_string(),
+                    AppStyles::Main.get(),
+                ),
+                Span::styled(d.name, AppStyles::TitleText.get()),
+                Span::styled(due_text, AppStyles::Main.get()),
+                Span::styled(format!(" {}", d.module), AppStyles::Accent.get()),
+            ])
+        })
+        .collect();
+    let calendar_block = Paragraph::new(deadline_lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Calendar")
+            .style(AppStyles::Main.get()),
+    );
+    f.render_widget(calendar_block, block_rect);
 }
 
 fn calendar_screen<B: Backend>(f: &mut Frame<B>, app: &mut App) {
-    let cal = Calendar::new();
+    let cal = Calendar::new(app.grade_state.get_deadlines());
     f.render_stateful_widget(cal, f.size(), &mut app.calendar_state);
     if app.calendar_state.show_popup {
         let empty_vec: Vec<CalendarEvent> = Vec::new();
@@ -171,3 +255,18 @@ fn film_screen<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     let m = FilmTracker::new();
     f.render_stateful_widget(m, f.size(), &mut app.film_state);
 }
+
+fn todo_screen<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let t = TodoList::new();
+    f.render_stateful_widget(t, f.size(), &mut app.todo_state);
+}
+
+fn rules_screen<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let r = RulesEditor::new();
+    f.render_stateful_widget(r, f.size(), &mut app.rules_state);
+}
+
+fn rates_screen<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let r = ExchangeRatesEditor::new();
+    f.render_stateful_widget(r, f.size(), &mut app.rates_state);
+}
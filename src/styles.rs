@@ -66,6 +66,7 @@ pub enum AppStyles {
     Backgroud,
     InvertedMain,
     ProgressBar,
+    ProgressBarWarning,
     CalendarCurDay,
     CalendarSelected,
     CalendarDeselected,
@@ -73,6 +74,8 @@ pub enum AppStyles {
     TitleTextDeactivated,
     ButtonSelected,
     ButtonDeselected,
+    Income,
+    Expense,
 }
 
 impl AppStyles {
@@ -83,6 +86,7 @@ impl AppStyles {
             AppStyles::Backgroud => Style::default().fg(COLORS.background),
             AppStyles::InvertedMain => Style::default().fg(COLORS.background).bg(COLORS.main),
             AppStyles::ProgressBar => Style::default().fg(COLORS.main).bg(COLORS.background),
+            AppStyles::ProgressBarWarning => Style::default().fg(Color::Red).bg(COLORS.background),
             AppStyles::CalendarCurDay => Style::default().fg(COLORS.accent),
             AppStyles::CalendarSelected => Style::default().fg(COLORS.main),
             AppStyles::CalendarDeselected => Style::default().fg(COLORS.accent),
@@ -94,6 +98,8 @@ impl AppStyles {
                 .add_modifier(Modifier::BOLD),
             AppStyles::ButtonDeselected => Style::default().bg(COLORS.accent).fg(COLORS.accent),
             AppStyles::ButtonSelected => Style::default().bg(COLORS.main).fg(COLORS.main),
+            AppStyles::Income => Style::default().fg(Color::Green),
+            AppStyles::Expense => Style::default().fg(Color::Red),
         }
     }
 }
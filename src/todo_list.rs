@@ -0,0 +1,308 @@
+use chrono::{Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::Span,
+    widgets::{BorderType, Borders, StatefulWidget},
+};
+
+use crate::{
+    db::{DbError, Todo, DB},
+    form::{render_form_popup, DateField, FormFieldStyle, FormState, FormValue, TextField},
+    recurrence::Recurrence,
+    styles::AppStyles,
+    util::{centered_rect, draw_rect_borders, generic_increment},
+};
+
+#[derive(Debug)]
+pub struct TodoState {
+    pub todos: Vec<Todo>,
+    pub selected: u32,
+    pub show_form: bool,
+    pub form_state: FormState,
+    editing: Option<i64>,
+    form_error: Option<String>,
+}
+
+impl TodoState {
+    pub async fn new(db: &mut DB) -> Result<TodoState, DbError> {
+        let mut form_state = FormState::new();
+        form_state.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            true,
+            FormFieldStyle::new("Description".to_owned()),
+        )));
+        form_state.add_field(Box::new(TextField::new(
+            "".to_owned(),
+            false,
+            FormFieldStyle::new("Repeat (daily/weekly mon,fri/monthly 15/every 3)".to_owned()),
+        )));
+        form_state.add_field(Box::new(DateField::new(
+            Utc::now(),
+            false,
+            FormFieldStyle::new("Due (repeating only)".to_owned()),
+        )));
+        Ok(TodoState {
+            todos: db.get_todos().await?,
+            selected: 0,
+            show_form: false,
+            form_state,
+            editing: None,
+            form_error: None,
+        })
+    }
+
+    async fn refresh(&mut self, db: &mut DB) -> Result<(), DbError> {
+        self.todos = db.get_todos().await?;
+        if self.selected as usize >= self.todos.len() {
+            self.selected = self.todos.len().saturating_sub(1) as u32;
+        }
+        Ok(())
+    }
+
+    fn num_outstanding(&self) -> usize {
+        self.todos.iter().filter(|t| !t.is_completed()).count()
+    }
+
+    pub fn get_selected_todo(&self) -> Option<&Todo> {
+        self.todos.get(self.selected as usize)
+    }
+
+    /// Outstanding todos that are overdue or due today
+    pub fn get_due_todos(&self, today: NaiveDate) -> Vec<&Todo> {
+        self.todos.iter().filter(|t| t.is_due(today)).collect()
+    }
+
+    pub fn increment_selected(&mut self, amount: i32) {
+        if self.todos.is_empty() {
+            return;
+        }
+        generic_increment(&mut self.selected, 0, self.todos.len() as u32 - 1, amount);
+    }
+
+    pub fn toggle_form(&mut self) {
+        self.show_form = !self.show_form;
+        if !self.show_form {
+            self.editing = None;
+            self.form_error = None;
+            self.form_state.reset_fields();
+        }
+    }
+
+    /// Opens the form filled in with the selected todo so it can be edited
+    pub fn open_edit_form(&mut self) {
+        if let Some(todo) = self.get_selected_todo() {
+            let id = todo.id;
+            let values = [
+                FormValue::Text(todo.description.clone()),
+                FormValue::Text(
+                    todo.recurrence
+                        .as_ref()
+                        .map_or("".to_owned(), |r| r.to_string()),
+                ),
+                FormValue::Date(todo.due_date.map_or(Utc::now(), |d| {
+                    Utc.from_utc_datetime(&d.and_time(NaiveTime::MIN))
+                })),
+            ];
+            for (field, value) in self.form_state.get_fields_mut().iter_mut().zip(values) {
+                field.set_value(value);
+            }
+            self.editing = Some(id);
+            self.show_form = true;
+        }
+    }
+
+    pub async fn submit_form(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let fields = self.form_state.get_fields();
+        let vals: Vec<&FormValue> = fields.iter().map(|f| f.get_internal_value()).collect();
+        if let [description, repeat, due] = vals.as_slice() {
+            let description = description.try_get_text_value().unwrap().trim().to_owned();
+            if description.is_empty() {
+                return Ok(());
+            }
+            let repeat = repeat.try_get_text_value().unwrap();
+            let recurrence = if repeat.trim().is_empty() {
+                None
+            } else {
+                match repeat.parse::<Recurrence>() {
+                    Ok(r) => Some(r),
+                    Err(e) => {
+                        self.form_error = Some(e);
+                        return Ok(());
+                    }
+                }
+            };
+            // only repeating todos are scheduled
+            let due_date = recurrence
+                .as_ref()
+                .map(|_| due.try_get_date_value().unwrap().date_naive());
+            let mut todo = Todo::new(description, recurrence, due_date);
+            match self.editing {
+                Some(id) => {
+                    todo.id = id;
+                    db.update_todo(&todo).await?;
+                }
+                None => db.add_todo(&todo).await?,
+            }
+            self.toggle_form();
+            self.refresh(db).await?;
+        }
+        Ok(())
+    }
+
+    /// Moves the selected outstanding todo up or down the list
+    pub async fn move_selected(&mut self, db: &mut DB, amount: i32) -> Result<(), DbError> {
+        let cur = self.selected as usize;
+        let num_outstanding = self.num_outstanding();
+        if cur >= num_outstanding {
+            return Ok(());
+        }
+        let other = if amount.is_negative() {
+            match cur.checked_sub(1) {
+                Some(i) => i,
+                None => return Ok(()),
+            }
+        } else if cur + 1 < num_outstanding {
+            cur + 1
+        } else {
+            return Ok(());
+        };
+        db.swap_todo_positions(&self.todos[cur], &self.todos[other])
+            .await?;
+        self.selected = other as u32;
+        self.refresh(db).await
+    }
+
+    /// Completes an outstanding todo, or moves a completed one back to the list
+    ///
+    /// Completing a repeating todo adds its next occurrence to the list, which
+    /// is taken away again if the todo is moved back before it is done
+    pub async fn toggle_completed(&mut self, db: &mut DB) -> Result<(), DbError> {
+        let todo = match self.get_selected_todo() {
+            Some(todo) => todo,
+            None => return Ok(()),
+        };
+        if todo.is_completed() {
+            db.uncomplete_todo(todo.id).await?;
+        } else {
+            let next = todo.next_occurrence(Local::now().date_naive());
+            db.complete_todo(todo.id, Utc::now(), next.as_ref()).await?;
+        }
+        self.refresh(db).await
+    }
+
+    pub async fn delete_selected(&mut self, db: &mut DB) -> Result<(), DbError> {
+        if let Some(id) = self.get_selected_todo().map(|t| t.id) {
+            db.delete_todo(id).await?;
+            self.refresh(db).await?;
+        }
+        Ok(())
+    }
+}
+
+pub struct TodoList;
+
+impl TodoList {
+    pub fn new() -> TodoList {
+        TodoList {}
+    }
+}
+
+impl StatefulWidget for TodoList {
+    type State = TodoState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        draw_rect_borders(
+            buf,
+            area,
+            Borders::ALL,
+            BorderType::Plain,
+            AppStyles::Main.get(),
+        );
+        let area = Rect {
+            x: area.x + 1,
+            y: area.y + 1,
+            width: area.width - 2,
+            height: area.height - 2,
+        };
+
+        // build every line first so the list can scroll to the selected todo
+        let num_outstanding = state.num_outstanding();
+        let today = Local::now().date_naive();
+        let mut lines: Vec<(String, Style)> = vec![(
+            format!("{:━^1$}", "Todo", area.width as usize),
+            AppStyles::Main.get(),
+        )];
+        let mut selected_line = 0;
+        for (i, todo) in state.todos.iter().enumerate() {
+            if i == num_outstanding {
+                lines.push((
+                    format!("{:━^1$}", "Done", area.width as usize),
+                    AppStyles::Main.get(),
+                ));
+            }
+            let mut style = if i == state.selected as usize {
+                selected_line = lines.len();
+                AppStyles::Main.get()
+            } else {
+                AppStyles::Accent.get()
+            };
+            if todo.is_due(today) {
+                style = style.add_modifier(Modifier::BOLD);
+            }
+            let text = match todo.completed_at {
+                Some(completed_at) => {
+                    let completed_text = completed_at
+                        .with_timezone(&Local)
+                        .format("%H:%M %a %d %b %Y")
+                        .to_string();
+                    format!(
+                        "[x] {:<1$}{2}",
+                        todo.description,
+                        (area.width as usize).saturating_sub(4 + completed_text.len()),
+                        completed_text
+                    )
+                }
+                None => {
+                    let schedule_text = match (&todo.recurrence, todo.due_date) {
+                        (Some(r), Some(d)) => format!("{} | due {}", r, d.format("%a %d %b %Y")),
+                        (Some(r), None) => r.to_string(),
+                        (None, _) => "".to_owned(),
+                    };
+                    format!(
+                        "[ ] {:<1$}{2}",
+                        todo.description,
+                        (area.width as usize).saturating_sub(4 + schedule_text.len()),
+                        schedule_text
+                    )
+                }
+            };
+            lines.push((text, style));
+        }
+
+        let height = area.height as usize;
+        let start = (selected_line + 1).saturating_sub(height);
+        for (i, (text, style)) in lines.iter().skip(start).take(height).enumerate() {
+            buf.set_stringn(area.x, area.y + i as u16, text, area.width as usize, *style);
+        }
+
+        if state.show_form {
+            let message = state
+                .form_error
+                .as_deref()
+                .map(|e| Span::styled(e, AppStyles::Expense.get()));
+            render_form_popup(
+                centered_rect(50, 50, area),
+                buf,
+                if state.editing.is_some() {
+                    " Edit Todo "
+                } else {
+                    " Enter New Todo "
+                },
+                &mut state.form_state,
+                message,
+            );
+        }
+    }
+}
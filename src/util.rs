@@ -2,8 +2,10 @@ use log::info;
 use std::{
     env,
     fs::File,
+    io,
     ops::{AddAssign, SubAssign},
     path::PathBuf,
+    process::Stdio,
 };
 
 use figlet_rs::FIGfont;
@@ -15,6 +17,8 @@ use tui::{
     widgets::{BorderType, Borders},
 };
 
+use crate::paths::PATHS;
+
 pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
@@ -190,17 +194,32 @@ async fn run_command_async(command: impl Into<String>) -> String {
     String::from_utf8_lossy(&output.stdout).into_owned()
 }
 
-pub fn getcwd() -> String {
-    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .to_str()
-        .unwrap()
-        .to_owned()
+/// Open a file in the desktop's default application without waiting for it to
+/// close
+pub fn open_file(path: &str) -> io::Result<()> {
+    let mut child = std::process::Command::new("xdg-open")
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // reap it once it exits so it doesn't linger as a zombie
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Replace a leading `~/` in a path typed by the user with their home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(rest),
+        None => PathBuf::from(path),
+    }
 }
 
 pub async fn get_calendar_events(year: i32, month: u32, num_of_days: i64) -> String {
     run_command_async(format!(
-        "python {}/src/get_events.py {} {} {}",
-        getcwd(),
+        "python \"{}\" {} {} {}",
+        PATHS.get_script_path("get_events.py").display(),
         year,
         month,
         num_of_days
@@ -235,7 +254,12 @@ pub fn get_xresources() -> String {
 }
 
 pub async fn search_imdb(name: &str) -> String {
-    run_command_async(format!("python {}/src/search_imdb.py {}", getcwd(), name)).await
+    run_command_async(format!(
+        "python \"{}\" {}",
+        PATHS.get_script_path("search_imdb.py").display(),
+        name
+    ))
+    .await
 }
 
 /// Increment a value by an amount between upper and lower bounds